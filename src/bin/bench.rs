@@ -0,0 +1,148 @@
+use std::{env, fs::OpenOptions, io::Write, process};
+
+use bevy_experiments::plugins::lesson_2::bench::{
+    compare, markdown_table, BenchBaseline, BENCH_BASELINE, BENCH_SCENARIOS,
+};
+
+const MEASUREMENTS: &str = "measurements/Measurements.md";
+const DEFAULT_FRAMES: usize = 120;
+/// Percent a scenario's frame time may grow before `--check` fails
+const DEFAULT_THRESHOLD: f64 = 10.0;
+
+const USAGE: &str = "usage: bench [--frames N] [--append] [--save-baseline] [--check] [--threshold PERCENT] [scenario...]
+runs headless, sprites are never extracted or queued so --check misses render regressions.
+--check compares against a baseline from the same build profile and machine, and skips 20k unless it is named";
+
+/// Runs the headless benchmark scenarios, all of them unless some are named,
+/// and prints a table of the results. `--append` also adds it to `Measurements.md`,
+/// `--save-baseline` stores the results as the new baseline and `--check` exits
+/// with 1 when a scenario got slower than the baseline by more than the threshold.
+/// Without names `--check` only runs the checked scenarios. Nothing is rendered,
+/// the check does not cover the render queue
+fn main() {
+    let mut frames = DEFAULT_FRAMES;
+    let mut append = false;
+    let mut save_baseline = false;
+    let mut check = false;
+    let mut threshold = DEFAULT_THRESHOLD;
+    let mut names = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => frames = n,
+                None => exit_with_usage(),
+            },
+            "--append" => append = true,
+            "--save-baseline" => save_baseline = true,
+            "--check" => check = true,
+            "--threshold" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => threshold = n,
+                None => exit_with_usage(),
+            },
+            _ if arg.starts_with("--") => exit_with_usage(),
+            _ => names.push(arg),
+        }
+    }
+
+    for name in names.iter() {
+        if !BENCH_SCENARIOS
+            .iter()
+            .any(|scenario| *name == scenario.name)
+        {
+            let known: Vec<&str> = BENCH_SCENARIOS.iter().map(|s| s.name).collect();
+            eprintln!("unknown scenario {}, pick from {}", name, known.join(" "));
+            process::exit(2);
+        }
+    }
+
+    // Fail before spending minutes on the runs
+    let baseline = if check {
+        let baseline = BenchBaseline::load(BENCH_BASELINE).and_then(|baseline| {
+            baseline.comparable(&BenchBaseline::here(Vec::new()))?;
+            Ok(baseline)
+        });
+        match baseline {
+            Ok(baseline) => Some(baseline),
+            Err(err) => {
+                eprintln!("could not use {}: {}", BENCH_BASELINE, err);
+                eprintln!("run with --save-baseline first");
+                process::exit(2);
+            }
+        }
+    } else {
+        None
+    };
+
+    let results: Vec<_> = BENCH_SCENARIOS
+        .iter()
+        .filter(|scenario| {
+            if names.is_empty() {
+                !check || scenario.checked
+            } else {
+                names.iter().any(|name| *name == scenario.name)
+            }
+        })
+        .map(|scenario| {
+            eprintln!("running {} for {} frames", scenario.name, frames);
+            scenario.run(frames)
+        })
+        .collect();
+
+    let table = markdown_table(&results);
+    print!("{}", table);
+
+    if append {
+        let written = OpenOptions::new()
+            .append(true)
+            .open(MEASUREMENTS)
+            .and_then(|mut file| write!(file, "\n{}", table));
+        if let Err(err) = written {
+            eprintln!("could not append to {}: {}", MEASUREMENTS, err);
+            process::exit(1);
+        }
+    }
+
+    if save_baseline {
+        // Scenarios that were not run keep their old numbers, unless those were measured elsewhere
+        let mut merged = BenchBaseline::here(results.clone());
+        if let Ok(old) = BenchBaseline::load(BENCH_BASELINE) {
+            if old.comparable(&merged).is_ok() {
+                merged
+                    .results
+                    .extend(old.results.into_iter().filter(|base| {
+                        !results
+                            .iter()
+                            .any(|result| result.scenario == base.scenario)
+                    }));
+            }
+        }
+
+        if let Err(err) = merged.save(BENCH_BASELINE) {
+            eprintln!("could not write {}: {}", BENCH_BASELINE, err);
+            process::exit(1);
+        }
+        eprintln!("saved the baseline to {}", BENCH_BASELINE);
+    }
+
+    if let Some(baseline) = baseline {
+        let comparison = compare(&baseline.results, &results, threshold);
+        println!();
+        for line in comparison.report.iter() {
+            println!("{}", line);
+        }
+
+        if !comparison.regressions.is_empty() {
+            for regression in comparison.regressions.iter() {
+                eprintln!("regression: {}", regression);
+            }
+            process::exit(1);
+        }
+    }
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
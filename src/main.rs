@@ -1,7 +1,5 @@
-mod plugins;
-
 use bevy::{prelude::*, window::PresentMode};
-use plugins::lesson_3::Lesson3Plugin;
+use bevy_experiments::plugins::lesson_3::Lesson3Plugin;
 
 fn main() {
     App::new()
@@ -1,21 +1,59 @@
+pub mod bench;
 pub mod bevy_radial_physics;
+pub mod camera;
+pub mod ecology;
 pub mod fast_rainbow_material;
+pub mod flow_field;
+pub mod merging;
+pub mod obstacles;
+pub mod patterns;
+pub mod perf_export;
 pub mod perf_log;
+pub mod pool;
+pub mod presets;
 // pub mod rainbow_material;
 // pub mod rainbow_sprite;
-// pub mod shapes;
+pub mod recording;
+pub mod saving;
+pub mod scenarios;
+pub mod selection;
+pub mod shapes;
 pub mod size_and_lifetime;
+pub mod soft_body;
+pub mod spatial_grid;
+pub mod spawner;
+pub mod steering;
+pub mod throwing;
+pub mod trace_export;
 
 use rand::Rng;
-use std::f32::consts::PI;
+use rand_pcg::Pcg64;
+use std::f32::consts::{FRAC_PI_2, PI};
 
 use bevy::prelude::*;
 
 use self::{
+    bench::BENCH_SCENARIOS,
     bevy_radial_physics::{CircleCollider, Force, RadialPhysicsPlugin},
+    camera::{MainCamera, PanZoomPlugin, WorldCursor},
+    ecology::{EcologyPlugin, Species},
     fast_rainbow_material::{SimpleMesh2d, SimpleMesh2dPlugin},
+    flow_field::FlowFieldPlugin,
+    merging::MergePlugin,
+    obstacles::ObstaclesPlugin,
+    patterns::PatternsPlugin,
+    perf_export::PerfExport,
     perf_log::PerfLogPlugin,
-    size_and_lifetime::{Health, SizeAndLifetimePlugin},
+    pool::{DotPool, PoolPlugin, Pooled},
+    presets::{FollowsPreset, PresetsPlugin, SpawnPresets},
+    recording::ReplayPlugin,
+    saving::SaveLoadPlugin,
+    selection::SelectionPlugin,
+    size_and_lifetime::{Health, Lifetime, LifetimeCurves, SizeAndLifetimePlugin, SplitOnDeath},
+    soft_body::{spawn_soft_grid, spawn_soft_ring, SoftBodyKind, SoftBodyPlugin},
+    spawner::{DotTemplate, EmissionShape, Spawner, SpawnerPlugin, ValueRange},
+    steering::{Boid, Flock, SteeringPlugin, SteeringTarget},
+    throwing::ThrowPlugin,
 };
 //
 //
@@ -26,12 +64,35 @@ pub struct Lesson2Plugin;
 impl Plugin for Lesson2Plugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(PerfLogPlugin)
+            .add_plugin(PanZoomPlugin)
             .add_plugin(RadialPhysicsPlugin)
             .add_plugin(SizeAndLifetimePlugin)
+            .add_plugin(PoolPlugin)
             .add_plugin(SimpleMesh2dPlugin)
-            .insert_resource(NextSpawnTime(0.0))
+            .add_plugin(SoftBodyPlugin)
+            .add_plugin(SteeringPlugin)
+            .add_plugin(EcologyPlugin)
+            .add_plugin(FlowFieldPlugin)
+            .add_plugin(ObstaclesPlugin)
+            .add_plugin(MergePlugin)
+            .add_plugin(SpawnerPlugin)
+            .add_plugin(PatternsPlugin)
+            .add_plugin(PresetsPlugin)
+            .add_plugin(ThrowPlugin)
+            .add_plugin(SelectionPlugin)
+            .add_plugin(SaveLoadPlugin)
+            .add_plugin(ReplayPlugin)
+            .init_resource::<SimRng>()
+            .init_resource::<PointerTool>()
+            .insert_resource(PerfExport::new(
+                "lesson_2",
+                BENCH_SCENARIOS
+                    .iter()
+                    .map(|scenario| scenario.dots())
+                    .collect(),
+            ))
             .add_startup_system(init_system)
-            // .add_startup_system(hot_start_system)
+            .add_system_to_stage(CoreStage::First, sim_rng_system)
             .add_system(input_system);
     }
 }
@@ -40,12 +101,69 @@ impl Plugin for Lesson2Plugin {
 //
 // Components
 
+/// Follows the cursor and sprays dots while the left button is held
+#[derive(Component)]
+struct CursorSpawner;
+
+/// Spawner left behind with Q, cleared with Backspace
+#[derive(Component)]
+struct PlacedSpawner;
+
 //
 //
 // Resources
 
-#[derive(Default)]
-struct NextSpawnTime(f64);
+/// What the plain left mouse button does, T toggles throwing and S toggles selecting
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum PointerTool {
+    #[default]
+    Spray,
+    Throw,
+    Select,
+}
+
+/// Seeds every random draw of the simulation, so a replay gets the same numbers.
+/// Seeding and the generator are fixed algorithms, recordings stay valid across
+/// Rust and `rand` versions
+#[derive(Clone, Copy)]
+pub struct SimRng {
+    pub seed: u64,
+    frame: u64,
+}
+
+impl Default for SimRng {
+    fn default() -> Self {
+        SimRng::new(rand::random())
+    }
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        SimRng { seed, frame: 0 }
+    }
+
+    /// Generator for this frame, every system asks for its own `stream`
+    /// so the numbers do not depend on the order the systems run in
+    pub fn get(&self, stream: &str) -> Pcg64 {
+        let stream = fnv1a(stream.as_bytes());
+        let hi = split_mix64(self.seed ^ split_mix64(self.frame));
+        let lo = split_mix64(hi ^ stream);
+        Pcg64::new(((hi as u128) << 64) | lo as u128, stream as u128)
+    }
+}
+
+fn split_mix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
 
 //
 //
@@ -53,40 +171,179 @@ struct NextSpawnTime(f64);
 
 fn init_system(mut commands: Commands) {
     // Camera
-    commands.spawn_bundle(Camera2dBundle::default());
+    commands
+        .spawn_bundle(Camera2dBundle::default())
+        .insert(MainCamera);
+
+    commands
+        .spawn()
+        .insert(Transform::default())
+        .insert(Spawner {
+            active: false,
+            ..default()
+        })
+        .insert(FollowsPreset)
+        .insert(CursorSpawner);
 }
 
-fn hot_start_system(mut commands: Commands) {
-    for _ in 0..1024 {
-        spawn_random_dot(&mut commands);
-    }
+fn sim_rng_system(mut sim_rng: ResMut<SimRng>) {
+    sim_rng.frame += 1;
 }
 
+#[allow(clippy::too_many_arguments)]
 fn input_system(
-    t: Res<Time>,
-    mut next_t: ResMut<NextSpawnTime>,
     windows: Res<Windows>,
+    world_cursor: Res<WorldCursor>,
     buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mut steering_target: ResMut<SteeringTarget>,
+    mut cursor_spawner: Query<(&mut Transform, &mut Spawner), With<CursorSpawner>>,
+    placed: Query<Entity, With<PlacedSpawner>>,
+    mut presets: ResMut<SpawnPresets>,
+    mut tool: ResMut<PointerTool>,
+    mut pool: ResMut<DotPool>,
+    sim_rng: Res<SimRng>,
     mut commands: Commands,
 ) {
-    const DELAY: f64 = 0.01;
+    let window = windows.get_primary().unwrap();
+    let mut rng = sim_rng.get("input");
+    let cursor = world_cursor.0.map(|pos| pos.extend(0.0));
 
-    if t.seconds_since_startup() >= next_t.0 {
-        let window = windows.get_primary().unwrap();
+    steering_target.0 = cursor.map(|pos| pos.truncate());
 
-        if buttons.pressed(MouseButton::Left) {
-            if let Some(pos) = window.cursor_position() {
-                next_t.0 = t.seconds_since_startup() + DELAY;
+    for (key, toggled) in [
+        (KeyCode::T, PointerTool::Throw),
+        (KeyCode::S, PointerTool::Select),
+    ] {
+        if keys.just_pressed(key) {
+            *tool = if *tool == toggled {
+                PointerTool::Spray
+            } else {
+                toggled
+            };
+        }
+    }
 
-                let pos = Vec3::new(
-                    pos.x - window.width() * 0.5,
-                    pos.y - window.height() * 0.5,
-                    0.0,
-                );
+    // Only spray with the plain left button, modifiers are used below
+    // and the other tools have their own plugins
+    let modifier = keys.pressed(KeyCode::LControl)
+        || keys.pressed(KeyCode::LAlt)
+        || keys.pressed(KeyCode::LShift);
+
+    for (mut trns, mut spawner) in cursor_spawner.iter_mut() {
+        if let Some(pos) = cursor {
+            trns.translation = pos;
+        }
+        spawner.active = cursor.is_some()
+            && !modifier
+            && *tool == PointerTool::Spray
+            && buttons.pressed(MouseButton::Left);
+    }
+
+    if keys.just_pressed(KeyCode::P) {
+        presets.next();
+    }
+
+    // Emitters
+    if keys.just_pressed(KeyCode::Q) {
+        if let Some(pos) = cursor {
+            spawn_fountain(&mut commands, pos);
+        }
+    }
+
+    if keys.just_pressed(KeyCode::Back) {
+        placed.for_each(|ntt| commands.entity(ntt).despawn());
+    }
 
-                spawn_random_dot_at(&mut commands, pos);
+    // Flocks
+    if keys.just_pressed(KeyCode::B) {
+        if let Some(pos) = steering_target.0 {
+            let flock = Flock {
+                target: if keys.pressed(KeyCode::LShift) {
+                    -1.0
+                } else {
+                    0.5
+                },
+                ..default()
+            };
+            spawn_flock(
+                &mut commands,
+                &mut pool,
+                &mut rng,
+                pos.extend(0.0),
+                500,
+                flock,
+            );
+        }
+    }
+
+    // Ecosystem
+    if keys.just_pressed(KeyCode::E) {
+        spawn_ecosystem(
+            &mut commands,
+            &mut pool,
+            &mut rng,
+            window.width(),
+            window.height(),
+        );
+    }
+
+    // Fireworks and asteroids
+    if keys.just_pressed(KeyCode::X) || keys.just_pressed(KeyCode::Z) {
+        if let Some(pos) = cursor {
+            if keys.just_pressed(KeyCode::X) {
+                let velo = Vec3::new(0.0, 400.0, 0.0);
+                if let Some(dot) = spawn_dot(&mut commands, &mut pool, pos, 32.0, velo, 0.0) {
+                    commands
+                        .entity(dot)
+                        .insert(Lifetime::new(1.0))
+                        .insert(LifetimeCurves::fade())
+                        .insert(SplitOnDeath {
+                            children: 24,
+                            spread: 300.0,
+                        });
+                }
+            } else if let Some(dot) =
+                spawn_dot(&mut commands, &mut pool, pos, 64.0, Vec3::ZERO, 0.0)
+            {
+                commands.entity(dot).insert(SplitOnDeath {
+                    children: 3,
+                    spread: 80.0,
+                });
+            }
+        }
+    }
+
+    // Modifiers spawn soft bodies instead of dots
+    if (keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::LAlt))
+        && buttons.just_pressed(MouseButton::Left)
+    {
+        if let Some(pos) = cursor {
+            if keys.pressed(KeyCode::LControl) {
+                let kind = SoftBodyKind::Pressure { pressure: 60.0 };
+                spawn_soft_ring(
+                    &mut commands,
+                    &mut pool,
+                    &mut rng,
+                    pos,
+                    80.0,
+                    24,
+                    12.0,
+                    kind,
+                );
             } else {
-                // cursor is not inside the window
+                let kind = SoftBodyKind::ShapeMatching { stiffness: 20.0 };
+                spawn_soft_grid(
+                    &mut commands,
+                    &mut pool,
+                    &mut rng,
+                    pos,
+                    6,
+                    6,
+                    16.0,
+                    12.0,
+                    kind,
+                );
             }
         }
     }
@@ -96,42 +353,127 @@ fn input_system(
 //
 // Helpers
 
-fn spawn_dot(commands: &mut Commands, pos: Vec3, size: f32, velo: Vec3, color_offset: f32) {
-    commands
-        .spawn_bundle((
-            SimpleMesh2d { t: color_offset },
-            Transform {
-                translation: pos,
-                scale: Vec3::splat(size),
-                ..default()
-            },
-            GlobalTransform::default(),
-            Visibility::default(),
-            ComputedVisibility::default(),
-        ))
-        .insert(Force { velo })
-        .insert(CircleCollider { r: 0.5 })
-        .insert(Health { value: size });
-}
+/// Reuses a pooled entity when there is one, `None` when the population cap refuses
+fn spawn_dot(
+    commands: &mut Commands,
+    pool: &mut DotPool,
+    pos: Vec3,
+    size: f32,
+    velo: Vec3,
+    color_offset: f32,
+) -> Option<Entity> {
+    if pool.room() == 0 {
+        return None;
+    }
 
-fn spawn_random_dot_at(mut commands: &mut Commands, pos: Vec3) {
-    let mut rng = rand::thread_rng();
+    let dot = (
+        SimpleMesh2d {
+            t: color_offset,
+            ..default()
+        },
+        Transform {
+            translation: pos,
+            scale: Vec3::splat(size),
+            ..default()
+        },
+        Visibility::default(),
+        Force { velo },
+        CircleCollider { r: 0.5 },
+        Health::new(size),
+        Lifetime::forever(),
+    );
 
-    let size: f32 = rng.gen_range(4.0..=32.0);
-    // let win_size = f32::min(window.width(), window.height());
-    // let size: f32 = rng.gen_range((win_size * 0.25)..=(win_size * 0.5));
+    let ntt = match pool.take() {
+        Some(ntt) => commands
+            .entity(ntt)
+            .remove::<Pooled>()
+            .insert_bundle(dot)
+            .id(),
+        None => commands
+            .spawn_bundle(dot)
+            .insert(GlobalTransform::default())
+            .insert(ComputedVisibility::default())
+            .id(),
+    };
 
-    let velo = Vec3::new(
-        rng.gen_range(-200.0..=200.0),
-        rng.gen_range(-200.0..=200.0),
-        rng.gen_range(-200.0..=200.0),
-    );
+    Some(ntt)
+}
 
+fn spawn_flock(
+    commands: &mut Commands,
+    pool: &mut DotPool,
+    rng: &mut impl Rng,
+    pos: Vec3,
+    count: usize,
+    flock: Flock,
+) {
     let color_offset = rng.gen::<f32>() * PI;
+    let max_speed = flock.max_speed;
+    let flock = commands.spawn().insert(flock).id();
 
-    spawn_dot(&mut commands, pos, size, velo, color_offset);
+    for _ in 0..count {
+        let offset = Vec3::new(
+            rng.gen_range(-64.0..=64.0),
+            rng.gen_range(-64.0..=64.0),
+            0.0,
+        );
+        let velo = Vec3::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0), 0.0)
+            .normalize_or_zero()
+            * max_speed;
+
+        match spawn_dot(commands, pool, pos + offset, 6.0, velo, color_offset) {
+            Some(boid) => {
+                commands.entity(boid).insert(Boid { flock });
+            }
+            None => break,
+        }
+    }
 }
 
-fn spawn_random_dot(mut commands: &mut Commands) {
-    spawn_random_dot_at(&mut commands, Vec3::ZERO);
+fn spawn_ecosystem(
+    commands: &mut Commands,
+    pool: &mut DotPool,
+    rng: &mut impl Rng,
+    width: f32,
+    height: f32,
+) {
+    for (species, count, size) in [(Species::Prey, 150, 8.0), (Species::Predator, 12, 24.0)] {
+        for _ in 0..count {
+            let pos = Vec3::new(
+                rng.gen_range(-0.5..=0.5) * width,
+                rng.gen_range(-0.5..=0.5) * height,
+                0.0,
+            );
+            let velo = Vec3::new(
+                rng.gen_range(-100.0..=100.0),
+                rng.gen_range(-100.0..=100.0),
+                0.0,
+            );
+            let color_offset = rng.gen::<f32>() * PI;
+
+            if let Some(dot) = spawn_dot(commands, pool, pos, size, velo, color_offset) {
+                commands.entity(dot).insert(species);
+            }
+        }
+    }
+}
+
+fn spawn_fountain(commands: &mut Commands, pos: Vec3) {
+    commands
+        .spawn()
+        .insert(Transform::from_translation(pos))
+        .insert(Spawner {
+            rate: 20.0,
+            shape: EmissionShape::Circle { r: 8.0 },
+            direction: FRAC_PI_2,
+            cone: 0.6,
+            template: DotTemplate {
+                size: ValueRange::new(4.0, 12.0),
+                speed: ValueRange::new(200.0, 320.0),
+                ttl: ValueRange::new(8.0, 16.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(PlacedSpawner);
 }
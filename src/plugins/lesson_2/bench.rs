@@ -0,0 +1,432 @@
+use std::{
+    env, fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    core::CorePlugin,
+    diagnostic::{DiagnosticId, Diagnostics, DiagnosticsPlugin},
+    input::InputPlugin,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    bevy_radial_physics::RadialPhysicsPlugin,
+    fast_rainbow_material::SimpleMesh2dPlugin,
+    patterns::spawn_hex_grid,
+    pool::{DotPool, PoolPlugin},
+    size_and_lifetime::{Curve, Lifetime, LifetimeCurves, SizeAndLifetimePlugin},
+    SimRng,
+};
+
+/// Committed results that `compare` checks fresh runs against, see `BenchBaseline`
+pub const BENCH_BASELINE: &str = "measurements/bench_baseline.json";
+
+/// Seconds per frame, fixed so every run simulates the same thing
+const DT: f64 = 1.0 / 60.0;
+/// Frames left out of the numbers, the first ones spawn everything
+const WARMUP_FRAMES: usize = 10;
+/// Longer than any run, dots only age
+const LIFETIME: f32 = 3600.0;
+
+/// Systems with their own timing diagnostic, in table order
+const TIMED_SYSTEMS: [(DiagnosticId, &str); 3] = [
+    (RadialPhysicsPlugin::STEP_TIME, "physics"),
+    (SizeAndLifetimePlugin::LIFETIME_TIME, "lifetime"),
+    (SimpleMesh2dPlugin::ANIMATION_TIME, "rainbow"),
+];
+
+//
+//
+// Scenarios
+
+/// Hex grid of dots at rest with physics, lifetime curves and the color animation.
+/// There is no renderer, so sprites are never extracted or queued and render changes
+/// like `queue_sprites` refactors need the perf overlay or an F10 session instead
+#[derive(Clone, Copy)]
+pub struct BenchScenario {
+    pub name: &'static str,
+    pub cols: usize,
+    pub rows: usize,
+    pub size: f32,
+    /// Run by `--check` when no scenarios are named, the slow ones are left to manual runs
+    pub checked: bool,
+}
+
+/// All of them fit the default arena
+pub const BENCH_SCENARIOS: [BenchScenario; 3] = [
+    BenchScenario {
+        name: "1k",
+        cols: 40,
+        rows: 25,
+        size: 8.0,
+        checked: true,
+    },
+    BenchScenario {
+        name: "5k",
+        cols: 100,
+        rows: 50,
+        size: 8.0,
+        checked: true,
+    },
+    BenchScenario {
+        name: "20k",
+        cols: 200,
+        rows: 100,
+        size: 6.0,
+        checked: false,
+    },
+];
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BenchResult {
+    pub scenario: String,
+    pub dots: usize,
+    pub frames: usize,
+    /// Average wall time of a whole frame in milliseconds
+    pub frame_time: f64,
+    /// Average milliseconds per frame of every system in `TIMED_SYSTEMS`
+    pub system_times: Vec<f64>,
+}
+
+impl BenchScenario {
+    pub fn dots(&self) -> usize {
+        self.cols * self.rows
+    }
+
+    /// Runs `frames` frames after the warmup, as fast as they go
+    pub fn run(&self, frames: usize) -> BenchResult {
+        let mut app = App::new();
+
+        let start = Instant::now();
+        let mut time = Time::default();
+        time.update_with_instant(start);
+
+        app.add_plugin(CorePlugin)
+            .add_plugin(InputPlugin)
+            .add_plugin(DiagnosticsPlugin)
+            .insert_resource(time)
+            .insert_resource(SimRng::new(0))
+            .add_plugin(RadialPhysicsPlugin)
+            .add_plugin(SizeAndLifetimePlugin)
+            .add_plugin(PoolPlugin)
+            .add_plugin(SimpleMesh2dPlugin);
+
+        app.world.resource_mut::<DotPool>().max_population = self.dots();
+
+        app.insert_resource(*self).add_startup_system(spawn_system);
+
+        let dt = Duration::from_secs_f64(DT);
+        let mut frame_time = 0.0;
+        let mut system_times = vec![0.0; TIMED_SYSTEMS.len()];
+
+        for frame in 1..=(WARMUP_FRAMES + frames) as u32 {
+            app.world
+                .resource_mut::<Time>()
+                .update_with_instant(start + dt * frame);
+
+            let frame_start = Instant::now();
+            app.update();
+            let elapsed = frame_start.elapsed().as_secs_f64() * 1000.0;
+
+            if frame as usize <= WARMUP_FRAMES {
+                continue;
+            }
+
+            frame_time += elapsed;
+            let diagnostics = app.world.resource::<Diagnostics>();
+            for (total, (id, _)) in system_times.iter_mut().zip(TIMED_SYSTEMS) {
+                *total += diagnostics.get(id).and_then(|d| d.value()).unwrap_or(0.0);
+            }
+        }
+
+        let frames_f64 = frames.max(1) as f64;
+        BenchResult {
+            scenario: self.name.to_string(),
+            dots: app.world.resource::<DotPool>().live(),
+            frames,
+            frame_time: frame_time / frames_f64,
+            system_times: system_times.iter().map(|t| t / frames_f64).collect(),
+        }
+    }
+}
+
+/// The results in `BENCH_BASELINE` and where they were measured,
+/// numbers from another build profile or machine say nothing about this one
+#[derive(Serialize, Deserialize)]
+pub struct BenchBaseline {
+    pub profile: String,
+    pub host: String,
+    pub results: Vec<BenchResult>,
+}
+
+impl BenchBaseline {
+    /// Results measured by this build on this machine
+    pub fn here(results: Vec<BenchResult>) -> Self {
+        let profile = if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        };
+
+        BenchBaseline {
+            profile: profile.to_string(),
+            host: hostname(),
+            results,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Whether `other` was measured the same way, the error says what differs
+    pub fn comparable(&self, other: &BenchBaseline) -> anyhow::Result<()> {
+        if self.profile != other.profile {
+            anyhow::bail!(
+                "measured with a {} build, this is a {} build",
+                self.profile,
+                other.profile
+            );
+        }
+        if self.host != other.host {
+            anyhow::bail!("measured on {}, this is {}", self.host, other.host);
+        }
+        Ok(())
+    }
+}
+
+/// Lines describing every scenario, plus one line for each regression
+#[derive(Default)]
+pub struct BenchComparison {
+    pub report: Vec<String>,
+    pub regressions: Vec<String>,
+}
+
+//
+//
+// Systems
+
+fn spawn_system(scenario: Res<BenchScenario>, mut pool: ResMut<DotPool>, mut commands: Commands) {
+    let curves = LifetimeCurves {
+        color_offset: Curve::new(vec![(0.0, 0.0), (1.0, 1.0)]),
+        ..default()
+    };
+
+    let dots = spawn_hex_grid(
+        &mut commands,
+        &mut pool,
+        Vec2::ZERO,
+        scenario.cols,
+        scenario.rows,
+        scenario.size,
+    );
+    for ntt in dots {
+        commands
+            .entity(ntt)
+            .insert(Lifetime::new(LIFETIME))
+            .insert(curves.clone());
+    }
+}
+
+//
+//
+// Helpers
+
+/// Same layout as `measurements/Measurements.md`, FPS here is simulated frames
+/// per second without any rendering
+pub fn markdown_table(results: &[BenchResult]) -> String {
+    let mut rows = vec![["Experiment", "FPS", "frame (ms)"]
+        .iter()
+        .map(|cell| cell.to_string())
+        .chain(
+            TIMED_SYSTEMS
+                .iter()
+                .map(|(_, name)| format!("{} (ms)", name)),
+        )
+        .collect::<Vec<String>>()];
+
+    for result in results.iter() {
+        let mut row = vec![
+            format!("Bench {} ({} dots)", result.scenario, result.dots),
+            format!("{:.0}", 1000.0 / result.frame_time),
+            format!("{:.2}", result.frame_time),
+        ];
+        row.extend(result.system_times.iter().map(|t| format!("{:.2}", t)));
+        rows.push(row);
+    }
+
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|col| rows.iter().map(|row| row[col].len()).max().unwrap_or(0))
+        .collect();
+
+    let line = |cells: Vec<String>| {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        format!("|{}|\n", cells.join("|"))
+    };
+
+    let mut table = line(rows[0].clone());
+    table += &line(widths.iter().map(|width| "-".repeat(*width)).collect());
+    for row in rows.into_iter().skip(1) {
+        table += &line(row);
+    }
+
+    table
+}
+
+/// Frame time of every scenario against the baseline, a scenario regressed when it got
+/// more than `threshold` percent slower. System times are noisier and only go into the report.
+/// Headless frames only, render queue regressions go unnoticed
+pub fn compare(
+    baseline: &[BenchResult],
+    results: &[BenchResult],
+    threshold: f64,
+) -> BenchComparison {
+    let mut comparison = BenchComparison::default();
+
+    for result in results.iter() {
+        let base = match baseline
+            .iter()
+            .find(|base| base.scenario == result.scenario)
+        {
+            Some(base) => base,
+            None => {
+                comparison
+                    .report
+                    .push(format!("{}: not in the baseline", result.scenario));
+                continue;
+            }
+        };
+
+        let change = percent_change(base.frame_time, result.frame_time);
+        let systems: Vec<String> = TIMED_SYSTEMS
+            .iter()
+            .zip(base.system_times.iter().zip(result.system_times.iter()))
+            .map(|((_, name), (before, after))| {
+                format!("{} {:+.1}%", name, percent_change(*before, *after))
+            })
+            .collect();
+        comparison.report.push(format!(
+            "{}: {:.2}ms -> {:.2}ms ({:+.1}%), {}",
+            result.scenario,
+            base.frame_time,
+            result.frame_time,
+            change,
+            systems.join(", ")
+        ));
+
+        if base.frames != result.frames {
+            comparison.report.push(format!(
+                "{}: the baseline ran {} frames, this run {}",
+                result.scenario, base.frames, result.frames
+            ));
+        }
+
+        if change > threshold {
+            comparison.regressions.push(format!(
+                "{} is {:.1}% slower than the baseline, more than {}%",
+                result.scenario, change, threshold
+            ));
+        }
+    }
+
+    comparison
+}
+
+/// Name of this machine, `unknown` when neither the system nor the environment tell
+fn hostname() -> String {
+    fs::read_to_string("/etc/hostname")
+        .ok()
+        .or_else(|| env::var("COMPUTERNAME").ok())
+        .or_else(|| env::var("HOSTNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn percent_change(before: f64, after: f64) -> f64 {
+    if before > 0.0 {
+        (after - before) / before * 100.0
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(scenario: &str, frame_time: f64) -> BenchResult {
+        BenchResult {
+            scenario: scenario.to_string(),
+            dots: 1000,
+            frames: 60,
+            frame_time,
+            system_times: vec![1.0; TIMED_SYSTEMS.len()],
+        }
+    }
+
+    #[test]
+    fn percent_change_is_relative_to_before() {
+        assert_eq!(percent_change(8.0, 10.0), 25.0);
+        assert_eq!(percent_change(8.0, 6.0), -25.0);
+    }
+
+    #[test]
+    fn percent_change_of_zero_baseline_is_zero() {
+        assert_eq!(percent_change(0.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn regression_needs_more_than_threshold() {
+        let baseline = [result("1k", 8.0)];
+
+        let at_threshold = compare(&baseline, &[result("1k", 10.0)], 25.0);
+        assert!(at_threshold.regressions.is_empty());
+
+        let over_threshold = compare(&baseline, &[result("1k", 10.1)], 25.0);
+        assert_eq!(over_threshold.regressions.len(), 1);
+    }
+
+    #[test]
+    fn missing_scenario_is_reported_not_failed() {
+        let comparison = compare(&[result("1k", 8.0)], &[result("5k", 40.0)], 10.0);
+
+        assert!(comparison.regressions.is_empty());
+        assert_eq!(comparison.report, vec!["5k: not in the baseline"]);
+    }
+
+    #[test]
+    fn baseline_from_elsewhere_is_not_comparable() {
+        let baseline = |profile: &str, host: &str| BenchBaseline {
+            profile: profile.to_string(),
+            host: host.to_string(),
+            results: vec![result("1k", 8.0)],
+        };
+        let here = baseline("release", "bench-box");
+
+        assert!(baseline("release", "bench-box").comparable(&here).is_ok());
+        assert!(baseline("debug", "bench-box").comparable(&here).is_err());
+        assert!(baseline("release", "laptop").comparable(&here).is_err());
+    }
+
+    #[test]
+    fn zero_baseline_never_regresses() {
+        let comparison = compare(&[result("1k", 0.0)], &[result("1k", 10.0)], 10.0);
+
+        assert!(comparison.regressions.is_empty());
+        assert_eq!(comparison.report.len(), 1);
+    }
+}
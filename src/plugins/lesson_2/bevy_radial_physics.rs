@@ -1,5 +1,20 @@
-use bevy::{prelude::*, utils::HashMap};
+use std::{mem, time::Instant};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics},
+    prelude::*,
+    utils::HashMap,
+};
 use rand::Rng;
+use serde::Deserialize;
+
+use super::{
+    perf_log::{PerfLogDiagnostics, PerfLogStat},
+    pool::Pooled,
+    spatial_grid::SpatialGrid,
+    trace_export::TraceSpans,
+    SimRng,
+};
 
 //
 //
@@ -7,71 +22,239 @@ use rand::Rng;
 
 pub struct RadialPhysicsPlugin;
 
+impl RadialPhysicsPlugin {
+    /// Milliseconds spent in `movement_system`
+    pub const STEP_TIME: DiagnosticId =
+        DiagnosticId::from_u128(105072962072818747544110905888906372060);
+}
+
 impl Plugin for RadialPhysicsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(movement_system);
+        app.init_resource::<SimRng>()
+            .init_resource::<PerfLogDiagnostics>()
+            .init_resource::<TraceSpans>()
+            .register_type::<Force>()
+            .register_type::<CircleCollider>()
+            .init_resource::<Bounds>()
+            .init_resource::<CollisionResponse>()
+            .add_event::<Collision>()
+            .add_startup_system(init_system)
+            .add_system(bounds_system.label(PhysicsSystem::Bounds))
+            .add_system(
+                movement_system
+                    .label(PhysicsSystem::Movement)
+                    .after(PhysicsSystem::Bounds),
+            )
+            .add_system(
+                free_movement_system
+                    .label(PhysicsSystem::Movement)
+                    .after(PhysicsSystem::Bounds),
+            );
+
+        app.world
+            .resource_mut::<PerfLogDiagnostics>()
+            .0
+            .push((Self::STEP_TIME, PerfLogStat::Average));
     }
 }
 
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum PhysicsSystem {
+    Bounds,
+    Movement,
+}
+
 //
 //
 // Components
 
-#[derive(Component, Clone, Copy)]
+#[derive(Component, Reflect, Default, Clone, Copy)]
+#[reflect(Component)]
 pub struct Force {
     pub velo: Vec3,
 }
 
-#[derive(Component, Clone, Copy)]
+#[derive(Component, Reflect, Default, Clone, Copy)]
+#[reflect(Component)]
 pub struct CircleCollider {
     pub r: f32,
 }
 
+/// Capsule from `a` to `b` in local space, used for walls
+#[derive(Component, Clone, Copy)]
+pub struct SegmentCollider {
+    pub a: Vec2,
+    pub b: Vec2,
+    pub r: f32,
+}
+
+/// Convex polygon in local space, points go counter-clockwise
+#[derive(Component, Clone)]
+pub struct PolygonCollider {
+    pub points: Vec<Vec2>,
+}
+
+/// Never moves and has infinite mass, dots bounce off it
+#[derive(Component)]
+pub struct Static;
+
 //
 //
 // Resources
 
+/// Arena the physics entities bounce around in, follows the primary window
+#[derive(Clone, Copy, Deserialize)]
+pub struct Bounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Bounds {
+            min: Vec2::new(-640.0, -360.0),
+            max: Vec2::new(640.0, 360.0),
+        }
+    }
+}
+
+impl Bounds {
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+}
+
+/// What happens when two dots overlap
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollisionResponse {
+    #[default]
+    Bounce,
+    /// Leave them overlapping and only send `Collision` events
+    Overlap,
+}
+
+//
+//
+// Events
+
+/// Sent whenever a dot runs into another dot or a static obstacle
+pub struct Collision {
+    pub a: Entity,
+    pub b: Entity,
+    /// Speed at which the two were closing in on each other
+    pub impact: f32,
+}
+
 //
 //
 // Systems
 
+/// Without a `DiagnosticsPlugin`, like in the headless scenarios, the step time is not measured
+fn init_system(diagnostics: Option<ResMut<Diagnostics>>) {
+    if let Some(mut diagnostics) = diagnostics {
+        diagnostics
+            .add(Diagnostic::new(RadialPhysicsPlugin::STEP_TIME, "physics", 20).with_suffix("ms"));
+    }
+}
+
+fn bounds_system(windows: Option<Res<Windows>>, mut bounds: ResMut<Bounds>) {
+    if let Some(win) = windows.as_ref().and_then(|windows| windows.get_primary()) {
+        let half_size = Vec2::new(win.width(), win.height()) * 0.5;
+        if bounds.min != -half_size || bounds.max != half_size {
+            bounds.min = -half_size;
+            bounds.max = half_size;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn movement_system(
-    windows: Res<Windows>,
+    bounds: Res<Bounds>,
+    response: Res<CollisionResponse>,
     time: Res<Time>,
-    // res_opt: Option<Res<(Time, Bounds)>>,
-    mut query: Query<(Entity, &mut Transform, &CircleCollider, &mut Force)>,
+    sim_rng: Res<SimRng>,
+    mut query: Query<
+        (Entity, &mut Transform, &CircleCollider, &mut Force),
+        (Without<Static>, Without<Pooled>),
+    >,
+    statics: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&CircleCollider>,
+            Option<&SegmentCollider>,
+            Option<&PolygonCollider>,
+        ),
+        With<Static>,
+    >,
+    mut collisions: EventWriter<Collision>,
+    diagnostics: Option<ResMut<Diagnostics>>,
+    spans: Res<TraceSpans>,
 ) {
+    let _span = spans.enter("movement_system");
+    let start = Instant::now();
+
+    let statics: Vec<(Entity, StaticShape)> = statics
+        .iter()
+        .filter_map(|(ntt, trns, circle, segment, polygon)| {
+            StaticShape::from_components(trns, circle, segment, polygon).map(|shape| (ntt, shape))
+        })
+        .collect();
+
+    let mut rng = sim_rng.get("physics");
+
     // Copy entities to a hash map
     let mut entities: HashMap<u32, (Vec3, Vec3)> = HashMap::new();
     query.for_each(|(ntt, trns, _, frc)| {
-        entities.insert(ntt.id(), (trns.translation.clone(), frc.velo.clone()));
+        entities.insert(ntt.id(), (trns.translation, frc.velo));
     });
 
+    let dt = time.delta().as_secs_f32();
+
+    // Dots only collide with the ones before them in query order, the grid finds the
+    // nearby ones. Everything moves during the step, the lookup reaches far enough for that
+    let order: Vec<(Entity, f32)> = query
+        .iter()
+        .map(|(ntt, trns, col, _)| (ntt, col.r * trns.scale.x))
+        .collect();
+    let max_r = order.iter().fold(0.0, |max, (_, r)| f32::max(max, *r));
+    let max_step = query.iter().fold(0.0, |max, (_, _, _, frc)| {
+        f32::max(max, frc.velo.length() * dt)
+    });
+    let reach = max_r + max_step * 2.0;
+
+    let mut grid = SpatialGrid::new(f32::max(reach * 2.0, 1.0));
+    for (i, (ntt, _)) in order.iter().enumerate() {
+        grid.insert(entities[&ntt.id()].0.truncate(), i);
+    }
+
+    let mut near = Vec::new();
+
     // Update entities in hash map
-    for (ntt, trns, col, _) in query.iter() {
+    for (i, &(ntt, r)) in order.iter().enumerate() {
         let (mut pos, mut velo) = entities.get(&ntt.id()).unwrap();
 
-        let r = col.r * trns.scale.x;
-
         // Move
-        pos += velo * time.delta().as_secs_f32();
+        pos += velo * dt;
         pos = Vec3::new(pos.x, pos.y, 0.0);
 
-        let win = windows.get_primary().unwrap();
-        let left = win.width() * -0.5;
-        let right = win.width() * 0.5;
-        let bottom = win.height() * -0.5;
-        let top = win.height() * 0.5;
-
-        // Collide with others
-        for (ntt_other, trns_other, col_other, _) in query.iter() {
-            if ntt_other == ntt {
-                // Do not collide with self
-                break;
+        let left = bounds.min.x;
+        let right = bounds.max.x;
+        let bottom = bounds.min.y;
+        let top = bounds.max.y;
+
+        // Collide with others, in query order like a pass over every pair would
+        near.clear();
+        grid.for_each_near(pos.truncate(), r + reach, |j| {
+            if j < i {
+                near.push(j);
             }
+        });
+        near.sort_unstable();
 
+        for &j in near.iter() {
+            let (ntt_other, r_other) = order[j];
             let (mut pos_other, mut velo_other) = entities.get(&ntt_other.id()).unwrap();
-            let r_other = col_other.r * trns_other.scale.x;
 
             let dist = Vec3::distance(pos, pos_other);
             let r_sum = r + r_other;
@@ -85,8 +268,6 @@ fn movement_system(
                     // their positions are the same,
                     // there is no direction away from each other
                     // so we generate a random one
-                    let mut rng = rand::thread_rng();
-
                     towards_self = Vec3::new(rng.gen(), rng.gen(), rng.gen()).normalize();
                     towards_other = Vec3::new(rng.gen(), rng.gen(), rng.gen()).normalize();
                 } else {
@@ -94,9 +275,26 @@ fn movement_system(
                     towards_other = -towards_self;
                 }
 
-                let temp = velo;
-                velo = velo_other;
-                velo_other = temp;
+                let impact = (velo - velo_other).dot(towards_self);
+
+                if *response == CollisionResponse::Overlap {
+                    collisions.send(Collision {
+                        a: ntt,
+                        b: ntt_other,
+                        impact: impact.max(0.0),
+                    });
+                    continue;
+                }
+
+                if impact > 0.0 {
+                    collisions.send(Collision {
+                        a: ntt,
+                        b: ntt_other,
+                        impact,
+                    });
+                }
+
+                mem::swap(&mut velo, &mut velo_other);
 
                 pos += towards_self * (dist - r_sum);
                 pos_other += towards_other * (dist - r_sum);
@@ -106,6 +304,24 @@ fn movement_system(
             }
         }
 
+        // Collide with static obstacles
+        for (ntt_static, shape) in statics.iter() {
+            if let Some((normal, depth)) = shape.contact(pos.truncate(), r) {
+                pos += (normal * depth).extend(0.0);
+
+                let along = velo.truncate().dot(normal);
+                if along < 0.0 {
+                    velo -= (normal * along * 2.0).extend(0.0);
+
+                    collisions.send(Collision {
+                        a: ntt,
+                        b: *ntt_static,
+                        impact: -along,
+                    });
+                }
+            }
+        }
+
         // Collide with bounds
 
         if pos.x - r <= left {
@@ -138,7 +354,149 @@ fn movement_system(
     // Write updates to entities
     for (ntt, mut trns, _, mut frc) in query.iter_mut() {
         let (new_pos, new_velo) = entities.get(&ntt.id()).unwrap();
-        trns.translation = new_pos.clone();
-        frc.velo = new_velo.clone();
+        trns.translation = *new_pos;
+        frc.velo = *new_velo;
+    }
+
+    if let Some(mut diagnostics) = diagnostics {
+        diagnostics.add_measurement(RadialPhysicsPlugin::STEP_TIME, || {
+            start.elapsed().as_secs_f64() * 1000.0
+        });
+    }
+}
+
+/// Moves entities that have a force but no collider, they only bounce off the bounds
+fn free_movement_system(
+    bounds: Res<Bounds>,
+    time: Res<Time>,
+    mut query: Query<(&mut Transform, &mut Force), Without<CircleCollider>>,
+) {
+    let dt = time.delta().as_secs_f32();
+
+    query.for_each_mut(|(mut trns, mut frc)| {
+        let mut pos = trns.translation + frc.velo * dt;
+
+        if pos.x < bounds.min.x || pos.x > bounds.max.x {
+            frc.velo.x = -frc.velo.x;
+            pos.x = pos.x.clamp(bounds.min.x, bounds.max.x);
+        }
+
+        if pos.y < bounds.min.y || pos.y > bounds.max.y {
+            frc.velo.y = -frc.velo.y;
+            pos.y = pos.y.clamp(bounds.min.y, bounds.max.y);
+        }
+
+        trns.translation = Vec3::new(pos.x, pos.y, 0.0);
+    });
+}
+
+//
+//
+// Helpers
+
+/// World space shape of a `Static` entity
+pub enum StaticShape {
+    Circle { center: Vec2, r: f32 },
+    Segment { a: Vec2, b: Vec2, r: f32 },
+    Polygon { points: Vec<Vec2> },
+}
+
+impl StaticShape {
+    pub fn from_components(
+        trns: &Transform,
+        circle: Option<&CircleCollider>,
+        segment: Option<&SegmentCollider>,
+        polygon: Option<&PolygonCollider>,
+    ) -> Option<Self> {
+        let to_world = |p: Vec2| trns.mul_vec3(p.extend(0.0)).truncate();
+
+        if let Some(segment) = segment {
+            Some(StaticShape::Segment {
+                a: to_world(segment.a),
+                b: to_world(segment.b),
+                r: segment.r,
+            })
+        } else if let Some(polygon) = polygon {
+            Some(StaticShape::Polygon {
+                points: polygon.points.iter().map(|p| to_world(*p)).collect(),
+            })
+        } else {
+            circle.map(|circle| StaticShape::Circle {
+                center: trns.translation.truncate(),
+                r: circle.r * trns.scale.x,
+            })
+        }
+    }
+
+    /// Normal pointing out of the shape and how deep a circle at `pos` sinks into it
+    pub fn contact(&self, pos: Vec2, r: f32) -> Option<(Vec2, f32)> {
+        match self {
+            StaticShape::Circle {
+                center,
+                r: r_static,
+            } => contact_with_point(pos, r + r_static, *center),
+            StaticShape::Segment { a, b, r: r_static } => {
+                contact_with_point(pos, r + r_static, closest_on_segment(pos, *a, *b))
+            }
+            StaticShape::Polygon { points } => {
+                let mut inside = true;
+                let mut nearest_face = (Vec2::ZERO, f32::MIN);
+                let mut nearest_point = Vec2::ZERO;
+                let mut nearest_dist = f32::MAX;
+
+                for i in 0..points.len() {
+                    let a = points[i];
+                    let b = points[(i + 1) % points.len()];
+
+                    let edge = b - a;
+                    let outward = Vec2::new(edge.y, -edge.x).normalize_or_zero();
+                    let separation = (pos - a).dot(outward);
+                    if separation > 0.0 {
+                        inside = false;
+                    }
+                    if separation > nearest_face.1 {
+                        nearest_face = (outward, separation);
+                    }
+
+                    let point = closest_on_segment(pos, a, b);
+                    let dist = pos.distance(point);
+                    if dist < nearest_dist {
+                        nearest_point = point;
+                        nearest_dist = dist;
+                    }
+                }
+
+                if inside {
+                    Some((nearest_face.0, r - nearest_face.1))
+                } else {
+                    contact_with_point(pos, r, nearest_point)
+                }
+            }
+        }
     }
 }
+
+fn contact_with_point(pos: Vec2, r: f32, point: Vec2) -> Option<(Vec2, f32)> {
+    let away = pos - point;
+    let dist = away.length();
+
+    if dist >= r {
+        None
+    } else if dist == 0.0 {
+        // Dead center, any direction will do
+        Some((Vec2::Y, r))
+    } else {
+        Some((away / dist, r - dist))
+    }
+}
+
+fn closest_on_segment(pos: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let ab = b - a;
+    let len_sq = ab.length_squared();
+    if len_sq == 0.0 {
+        return a;
+    }
+
+    let t = ((pos - a).dot(ab) / len_sq).clamp(0.0, 1.0);
+    a + ab * t
+}
@@ -0,0 +1,124 @@
+use bevy::{
+    input::{
+        mouse::{MouseScrollUnit, MouseWheel},
+        InputSystem,
+    },
+    prelude::*,
+};
+
+//
+//
+// Plugin
+
+/// Middle-drag pans the camera, scrolling zooms around the cursor.
+/// `WorldCursor` holds the cursor in world space for everything else to use
+pub struct PanZoomPlugin;
+
+impl Plugin for PanZoomPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldCursor>()
+            .add_system_to_stage(CoreStage::PreUpdate, cursor_system.after(InputSystem))
+            .add_system(pan_zoom_system);
+    }
+}
+
+const ZOOM_STEP: f32 = 1.1;
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 10.0;
+/// Scroll distance of a touchpad that counts as one mouse wheel line
+const PIXELS_PER_LINE: f32 = 32.0;
+
+//
+//
+// Components
+
+/// The camera the cursor is mapped through
+#[derive(Component)]
+pub struct MainCamera;
+
+//
+//
+// Resources
+
+/// Cursor position in world space, `None` when the cursor is outside the window
+#[derive(Default)]
+pub struct WorldCursor(pub Option<Vec2>);
+
+//
+//
+// Systems
+
+/// Runs before the camera moves this frame, so the cursor matches what is on screen
+fn cursor_system(
+    windows: Res<Windows>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut world_cursor: ResMut<WorldCursor>,
+) {
+    let window = windows.get_primary().unwrap();
+
+    world_cursor.0 = match (window.cursor_position(), cameras.get_single()) {
+        (Some(pos), Ok((camera, cam_trns))) => {
+            let size = Vec2::new(window.width(), window.height());
+            let ndc = pos / size * 2.0 - Vec2::ONE;
+
+            let ndc_to_world = cam_trns.compute_matrix() * camera.projection_matrix().inverse();
+            Some(ndc_to_world.project_point3(ndc.extend(-1.0)).truncate())
+        }
+        _ => None,
+    };
+}
+
+fn pan_zoom_system(
+    windows: Res<Windows>,
+    buttons: Res<Input<MouseButton>>,
+    mut wheel: EventReader<MouseWheel>,
+    world_cursor: Res<WorldCursor>,
+    mut last_cursor: Local<Option<Vec2>>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    let window = windows.get_primary().unwrap();
+    let cursor = window.cursor_position();
+
+    let lines: f32 = wheel
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        })
+        .sum();
+
+    let (mut trns, mut proj) = match cameras.get_single_mut() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+
+    // Pan, the world under the cursor moves along with it
+    if buttons.pressed(MouseButton::Middle) {
+        if let (Some(last), Some(cursor)) = (*last_cursor, cursor) {
+            trns.translation -= ((cursor - last) * proj.scale).extend(0.0);
+        }
+    }
+    *last_cursor = cursor;
+
+    // Zoom, the point under the cursor stays where it is
+    if lines != 0.0 {
+        let old_scale = proj.scale;
+        proj.scale = (old_scale * ZOOM_STEP.powf(-lines)).clamp(MIN_ZOOM, MAX_ZOOM);
+
+        if let Some(anchor) = world_cursor.0 {
+            let center = trns.translation.truncate();
+            let center = anchor + (center - anchor) * proj.scale / old_scale;
+            trns.translation = center.extend(trns.translation.z);
+        }
+    }
+}
+
+//
+//
+// Helpers
+
+/// Back to the origin at scale 1, where world and window pixels line up
+pub fn reset_camera(trns: &mut Transform, proj: &mut OrthographicProjection) {
+    trns.translation = Vec3::new(0.0, 0.0, trns.translation.z);
+    proj.scale = 1.0;
+}
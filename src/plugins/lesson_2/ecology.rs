@@ -0,0 +1,344 @@
+use std::{collections::VecDeque, f32::consts::PI};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics},
+    prelude::*,
+    utils::HashSet,
+};
+use rand::Rng;
+
+use super::{
+    bevy_radial_physics::{CircleCollider, Force, PhysicsSystem},
+    perf_log::{spawn_bar_panel, PerfLogDiagnostics, PerfLogStat, PANEL_STACK_BOTTOM},
+    pool::DotPool,
+    size_and_lifetime::{DeathCause, Died, Health, LifetimeSystem},
+    spatial_grid::SpatialGrid,
+    spawn_dot, SimRng,
+};
+
+//
+//
+// Plugin
+
+/// Treats `Health` as energy: dots burn it over time, predators eat prey to refill it
+/// and every dot splits in two once it has enough. The overlay plots both populations
+pub struct EcologyPlugin;
+
+impl EcologyPlugin {
+    pub const PREDATOR_COUNT: DiagnosticId =
+        DiagnosticId::from_u128(118227914371418617468316212146318311431);
+    pub const PREY_COUNT: DiagnosticId =
+        DiagnosticId::from_u128(233468153702297434622981387012463735061);
+}
+
+impl Plugin for EcologyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EcologySettings>()
+            .init_resource::<PerfLogDiagnostics>()
+            .init_resource::<PopulationHistory>()
+            .add_startup_system(init_system)
+            .add_system(
+                ecology_system
+                    .label(LifetimeSystem::Deaths)
+                    .after(PhysicsSystem::Movement),
+            )
+            .add_system(population_system)
+            .add_system(population_graph_system.after(population_system));
+
+        app.world.resource_mut::<PerfLogDiagnostics>().0.extend([
+            (Self::PREDATOR_COUNT, PerfLogStat::Value),
+            (Self::PREY_COUNT, PerfLogStat::Value),
+        ]);
+    }
+}
+
+/// Seconds between two columns of the population graph
+const POPULATION_SAMPLE: f32 = 0.25;
+const POPULATION_COLUMNS: usize = 100;
+const POPULATION_SIZE: Vec2 = Vec2::new(300.0, 60.0);
+
+//
+//
+// Components
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum Species {
+    Predator,
+    Prey,
+}
+
+/// Column of the population graph of one species
+#[derive(Component)]
+struct PopulationBar(Species, usize);
+
+//
+//
+// Resources
+
+pub struct SpeciesTraits {
+    /// Energy gained per second, negative values burn energy
+    pub metabolism: f32,
+    /// Energy at which a dot splits into two
+    pub reproduce_at: f32,
+    /// Share of a prey's energy a predator gets from eating it
+    pub efficiency: f32,
+}
+
+pub struct EcologySettings {
+    pub predator: SpeciesTraits,
+    pub prey: SpeciesTraits,
+}
+
+impl EcologySettings {
+    pub fn traits(&self, species: Species) -> &SpeciesTraits {
+        match species {
+            Species::Predator => &self.predator,
+            Species::Prey => &self.prey,
+        }
+    }
+}
+
+impl Default for EcologySettings {
+    fn default() -> Self {
+        EcologySettings {
+            predator: SpeciesTraits {
+                metabolism: -1.5,
+                reproduce_at: 40.0,
+                efficiency: 0.8,
+            },
+            prey: SpeciesTraits {
+                // Grazing
+                metabolism: 1.0,
+                reproduce_at: 16.0,
+                efficiency: 0.0,
+            },
+        }
+    }
+}
+
+/// Predator and prey counts of the last `POPULATION_COLUMNS` samples, oldest first
+struct PopulationHistory {
+    samples: VecDeque<(usize, usize)>,
+    timer: Timer,
+}
+
+impl Default for PopulationHistory {
+    fn default() -> Self {
+        PopulationHistory {
+            samples: VecDeque::with_capacity(POPULATION_COLUMNS),
+            timer: Timer::from_seconds(POPULATION_SAMPLE, true),
+        }
+    }
+}
+
+//
+//
+// Systems
+
+fn init_system(mut commands: Commands, diagnostics: Option<ResMut<Diagnostics>>) {
+    // Prey below, predators on top
+    for (i, species) in [Species::Prey, Species::Predator].into_iter().enumerate() {
+        spawn_bar_panel(
+            &mut commands,
+            PANEL_STACK_BOTTOM + i as f32 * (POPULATION_SIZE.y + 16.0),
+            POPULATION_SIZE,
+            POPULATION_COLUMNS,
+            |column| PopulationBar(species, column),
+        );
+    }
+
+    if let Some(mut diagnostics) = diagnostics {
+        diagnostics.add(Diagnostic::new(
+            EcologyPlugin::PREDATOR_COUNT,
+            "predators",
+            20,
+        ));
+        diagnostics.add(Diagnostic::new(EcologyPlugin::PREY_COUNT, "prey", 20));
+    }
+}
+
+fn ecology_system(
+    time: Res<Time>,
+    settings: Res<EcologySettings>,
+    mut query: Query<(
+        Entity,
+        &Species,
+        &mut Transform,
+        &mut Health,
+        &CircleCollider,
+        &Force,
+    )>,
+    mut pool: ResMut<DotPool>,
+    mut deaths: EventWriter<Died>,
+    sim_rng: Res<SimRng>,
+    mut commands: Commands,
+) {
+    let dt = time.delta().as_secs_f32();
+
+    let prey: Vec<(Entity, Vec2, f32, f32, f32)> = query
+        .iter()
+        .filter(|(_, species, ..)| **species == Species::Prey)
+        .map(|(ntt, _, trns, health, col, _)| {
+            (
+                ntt,
+                trns.translation.truncate(),
+                col.r * trns.scale.x,
+                health.value,
+                health.max,
+            )
+        })
+        .collect();
+
+    let mut grid = SpatialGrid::new(64.0);
+    for (i, (_, pos, ..)) in prey.iter().enumerate() {
+        grid.insert(*pos, i);
+    }
+
+    // Predation
+    let mut eaten = HashSet::new();
+    for (_, species, trns, mut health, col, _) in query.iter_mut() {
+        if *species != Species::Predator {
+            continue;
+        }
+
+        let pos = trns.translation.truncate();
+        let r = col.r * trns.scale.x;
+
+        grid.for_each_near(pos, r * 2.0 + 1.0, |i| {
+            let (prey_ntt, prey_pos, prey_r, prey_energy, _) = prey[i];
+
+            // Physics keeps dots exactly touching, so allow a little slack
+            if prey_r >= r || pos.distance(prey_pos) > r + prey_r + 1.0 {
+                return;
+            }
+
+            if eaten.insert(prey_ntt) {
+                health.value += prey_energy * settings.predator.efficiency;
+                health.max = health.max.max(health.value);
+            }
+        });
+    }
+
+    for (ntt, pos, _, _, max) in prey.iter() {
+        if eaten.contains(ntt) {
+            deaths.send(Died {
+                entity: *ntt,
+                position: pos.extend(0.0),
+                size: *max,
+                cause: DeathCause::Eaten,
+            });
+        }
+    }
+
+    // Metabolism and reproduction
+    let mut rng = sim_rng.get("ecology");
+    for (ntt, species, mut trns, mut health, _, frc) in query.iter_mut() {
+        if eaten.contains(&ntt) {
+            continue;
+        }
+
+        let traits = settings.traits(*species);
+        health.value += traits.metabolism * dt;
+
+        if health.value <= 0.0 {
+            deaths.send(Died {
+                entity: ntt,
+                position: trns.translation,
+                size: health.max,
+                cause: DeathCause::Starved,
+            });
+            continue;
+        }
+
+        // Over the population cap they wait with splitting
+        if health.value >= traits.reproduce_at && pool.room() > 0 {
+            health.value *= 0.5;
+            health.max = health.value;
+
+            let dir = Vec3::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0), 0.0)
+                .normalize_or_zero();
+            let child_pos = trns.translation + dir * health.value;
+            let child_velo = frc.velo + dir * 50.0;
+
+            let color_offset = rng.gen::<f32>() * PI;
+            if let Some(child) = spawn_dot(
+                &mut commands,
+                &mut pool,
+                child_pos,
+                health.value,
+                child_velo,
+                color_offset,
+            ) {
+                commands.entity(child).insert(*species);
+            }
+        }
+
+        trns.scale = Vec3::splat(health.value);
+    }
+}
+
+fn population_system(
+    time: Res<Time>,
+    query: Query<&Species>,
+    mut history: ResMut<PopulationHistory>,
+    diagnostics: Option<ResMut<Diagnostics>>,
+) {
+    let mut predators = 0;
+    let mut prey = 0;
+    query.for_each(|species| match species {
+        Species::Predator => predators += 1,
+        Species::Prey => prey += 1,
+    });
+
+    if let Some(mut diagnostics) = diagnostics {
+        diagnostics.add_measurement(EcologyPlugin::PREDATOR_COUNT, || predators as f64);
+        diagnostics.add_measurement(EcologyPlugin::PREY_COUNT, || prey as f64);
+    }
+
+    if history.timer.tick(time.delta()).just_finished() {
+        if history.samples.len() == POPULATION_COLUMNS {
+            history.samples.pop_front();
+        }
+        history.samples.push_back((predators, prey));
+    }
+}
+
+/// Each species is scaled to its own peak, newest samples on the right
+fn population_graph_system(
+    history: Res<PopulationHistory>,
+    mut bars: Query<(&PopulationBar, &mut Style, &mut UiColor)>,
+) {
+    let count = |species: Species, (predators, prey): (usize, usize)| match species {
+        Species::Predator => predators,
+        Species::Prey => prey,
+    };
+    let peak = |species: Species| {
+        history
+            .samples
+            .iter()
+            .map(|sample| count(species, *sample))
+            .max()
+            .unwrap_or(0)
+            .max(1)
+    };
+    let (predator_peak, prey_peak) = (peak(Species::Predator), peak(Species::Prey));
+
+    // Columns without a sample yet stay empty on the left
+    let empty = POPULATION_COLUMNS - history.samples.len();
+    for (bar, mut style, mut color) in bars.iter_mut() {
+        let PopulationBar(species, column) = *bar;
+        let peak = match species {
+            Species::Predator => predator_peak,
+            Species::Prey => prey_peak,
+        };
+        let share = column.checked_sub(empty).map_or(0.0, |i| {
+            count(species, history.samples[i]) as f32 / peak as f32
+        });
+
+        style.size.height = Val::Px(share * POPULATION_SIZE.y);
+        color.0 = match species {
+            Species::Predator => Color::rgb(0.9, 0.3, 0.3),
+            Species::Prey => Color::rgb(0.3, 0.8, 0.9),
+        };
+    }
+}
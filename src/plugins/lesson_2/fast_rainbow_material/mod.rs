@@ -1,11 +1,26 @@
 mod render;
 
+use std::{sync::atomic::Ordering, time::Instant};
+
 use bevy::{
+    core_pipeline::core_2d::Transparent2d,
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics},
     prelude::*,
-    reflect::{TypeUuid}, render::{RenderStage, render_resource::SpecializedRenderPipelines, RenderApp, render_phase::AddRenderCommand}, core_pipeline::core_2d::Transparent2d,
+    reflect::TypeUuid,
+    render::{
+        render_phase::AddRenderCommand, render_resource::SpecializedRenderPipelines, RenderApp,
+        RenderStage,
+    },
 };
 
-use self::render::{SpriteMeta, SimpleMesh2dPipeline, ExtractedSprites, DrawSprite, queue_sprites};
+use self::render::{
+    queue_sprites, DrawSprite, ExtractedSprites, SimpleMesh2dPipeline, SimpleMesh2dStats,
+    SpriteMeta,
+};
+use super::{
+    perf_log::{PerfLogDiagnostics, PerfLogStat},
+    trace_export::TraceSpans,
+};
 
 pub use self::render::SimpleMesh2d;
 
@@ -15,6 +30,18 @@ pub const SPRITE_SHADER_HANDLE: HandleUntyped =
 #[derive(Default)]
 pub struct SimpleMesh2dPlugin;
 
+impl SimpleMesh2dPlugin {
+    /// Sprites extracted to the render world last frame
+    pub const EXTRACTED: DiagnosticId =
+        DiagnosticId::from_u128(177765827657785859065267967774361003199);
+    /// Milliseconds spent in `queue_sprites` last frame
+    pub const QUEUE_TIME: DiagnosticId =
+        DiagnosticId::from_u128(15533646233460998757361855645114642998);
+    /// Milliseconds spent in `rainbow_system`
+    pub const ANIMATION_TIME: DiagnosticId =
+        DiagnosticId::from_u128(246180734939006385826127416306713790211);
+}
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
 pub enum SpriteSystem {
     ExtractSprites,
@@ -22,13 +49,33 @@ pub enum SpriteSystem {
 
 impl Plugin for SimpleMesh2dPlugin {
     fn build(&self, app: &mut App) {
-        let mut shaders = app.world.resource_mut::<Assets<Shader>>();
-        let sprite_shader = Shader::from_wgsl(include_str!("render/sprite.wgsl"));
-        shaders.set_untracked(SPRITE_SHADER_HANDLE, sprite_shader);
-        app.add_asset::<TextureAtlas>();
+        // Headless apps, like the benchmarks, have no renderer and only get the animation
+        if let Some(mut shaders) = app.world.get_resource_mut::<Assets<Shader>>() {
+            let sprite_shader = Shader::from_wgsl(include_str!("render/sprite.wgsl"));
+            shaders.set_untracked(SPRITE_SHADER_HANDLE, sprite_shader);
+            app.add_asset::<TextureAtlas>();
+        }
+        let stats = SimpleMesh2dStats::default();
+        let spans = app
+            .world
+            .get_resource_or_insert_with(TraceSpans::default)
+            .clone();
+        app.register_type::<SimpleMesh2d>()
+            .insert_resource(stats.clone())
+            .init_resource::<PerfLogDiagnostics>()
+            .add_startup_system(diagnostics_init_system)
+            .add_system(diagnostics_system);
+
+        app.world.resource_mut::<PerfLogDiagnostics>().0.extend([
+            (Self::EXTRACTED, PerfLogStat::Value),
+            (Self::QUEUE_TIME, PerfLogStat::Average),
+            (Self::ANIMATION_TIME, PerfLogStat::Average),
+        ]);
 
         if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
             render_app
+                .insert_resource(stats)
+                .insert_resource(spans)
                 .init_resource::<SimpleMesh2dPipeline>()
                 .init_resource::<SpecializedRenderPipelines<SimpleMesh2dPipeline>>()
                 .init_resource::<SpriteMeta>()
@@ -45,8 +92,49 @@ impl Plugin for SimpleMesh2dPlugin {
     }
 }
 
-fn rainbow_system(time: Res<Time>, mut query: Query<&mut SimpleMesh2d>) {
+fn rainbow_system(
+    time: Res<Time>,
+    mut query: Query<&mut SimpleMesh2d>,
+    diagnostics: Option<ResMut<Diagnostics>>,
+    spans: Res<TraceSpans>,
+) {
+    let _span = spans.enter("rainbow_system");
+    let start = Instant::now();
+
     for mut spr in query.iter_mut() {
         spr.t += time.delta().as_secs_f32();
     }
+
+    if let Some(mut diagnostics) = diagnostics {
+        diagnostics.add_measurement(SimpleMesh2dPlugin::ANIMATION_TIME, || {
+            start.elapsed().as_secs_f64() * 1000.0
+        });
+    }
+}
+
+fn diagnostics_init_system(diagnostics: Option<ResMut<Diagnostics>>) {
+    if let Some(mut diagnostics) = diagnostics {
+        diagnostics.add(Diagnostic::new(
+            SimpleMesh2dPlugin::EXTRACTED,
+            "sprites",
+            20,
+        ));
+        diagnostics.add(
+            Diagnostic::new(SimpleMesh2dPlugin::QUEUE_TIME, "render queue", 20).with_suffix("ms"),
+        );
+        diagnostics.add(
+            Diagnostic::new(SimpleMesh2dPlugin::ANIMATION_TIME, "rainbow", 20).with_suffix("ms"),
+        );
+    }
+}
+
+/// Turns the numbers the render world left in `SimpleMesh2dStats` into diagnostics
+fn diagnostics_system(stats: Res<SimpleMesh2dStats>, diagnostics: Option<ResMut<Diagnostics>>) {
+    if let Some(mut diagnostics) = diagnostics {
+        let extracted = stats.extracted.load(Ordering::Relaxed);
+        let queue_time = stats.queue_time.load(Ordering::Relaxed);
+
+        diagnostics.add_measurement(SimpleMesh2dPlugin::EXTRACTED, || extracted as f64);
+        diagnostics.add_measurement(SimpleMesh2dPlugin::QUEUE_TIME, || queue_time as f64 / 1e6);
+    }
 }
@@ -1,3 +1,11 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
 use bevy::{
     core_pipeline::core_2d::Transparent2d,
     ecs::{
@@ -24,12 +32,27 @@ use bytemuck::{Pod, Zeroable};
 use copyless::VecHelper;
 use fixedbitset::FixedBitSet;
 
+use super::super::trace_export::TraceSpans;
 use super::SPRITE_SHADER_HANDLE;
 
 /// A marker component
-#[derive(Component, Default)]
+#[derive(Component, Reflect, Clone, Copy)]
+#[reflect(Component)]
 pub struct SimpleMesh2d {
     pub t: f32,
+    pub alpha: f32,
+    /// Draws a white rim, 0 for none and 1 for full
+    pub highlight: f32,
+}
+
+impl Default for SimpleMesh2d {
+    fn default() -> Self {
+        SimpleMesh2d {
+            t: 0.0,
+            alpha: 1.0,
+            highlight: 0.0,
+        }
+    }
 }
 
 /// Custom pipeline
@@ -113,6 +136,10 @@ impl SpecializedRenderPipeline for SimpleMesh2dPipeline {
             VertexFormat::Float32x2,
             // t
             VertexFormat::Float32,
+            // alpha
+            VertexFormat::Float32,
+            // highlight
+            VertexFormat::Float32,
         ];
 
         if key.contains(SimpleMesh2dPipelineKey::COLORED) {
@@ -172,6 +199,8 @@ pub struct ExtractedSprite {
     pub entity: Entity,
     pub transform: GlobalTransform,
     pub t: f32,
+    pub alpha: f32,
+    pub highlight: f32,
 }
 
 #[derive(Default)]
@@ -179,11 +208,23 @@ pub struct ExtractedSprites {
     pub sprites: Vec<ExtractedSprite>,
 }
 
+/// Lives in both the main and the render world, so the main world can read
+/// what the render world did last frame
+#[derive(Clone, Default)]
+pub struct SimpleMesh2dStats {
+    pub extracted: Arc<AtomicUsize>,
+    /// Nanoseconds spent in `queue_sprites`
+    pub queue_time: Arc<AtomicU64>,
+}
+
 pub fn extract_sprites(
     mut extracted_sprites: ResMut<ExtractedSprites>,
+    stats: Res<SimpleMesh2dStats>,
+    spans: Res<TraceSpans>,
     // texture_atlases: Extract<Res<Assets<TextureAtlas>>>,
     sprite_query: Extract<Query<(Entity, &ComputedVisibility, &SimpleMesh2d, &GlobalTransform)>>,
 ) {
+    let _span = spans.enter("extract_sprites");
     extracted_sprites.sprites.clear();
     for (entity, visibility, simple_mesh_2d, transform) in sprite_query.iter() {
         if !visibility.is_visible() {
@@ -194,8 +235,13 @@ pub fn extract_sprites(
             entity,
             transform: *transform,
             t: simple_mesh_2d.t,
+            alpha: simple_mesh_2d.alpha,
+            highlight: simple_mesh_2d.highlight,
         });
     }
+    stats
+        .extracted
+        .store(extracted_sprites.sprites.len(), Ordering::Relaxed);
 }
 
 #[repr(C)]
@@ -204,6 +250,8 @@ struct SpriteVertex {
     pub position: [f32; 3],
     pub uv: [f32; 2],
     pub t: f32,
+    pub alpha: f32,
+    pub highlight: f32,
 }
 
 // #[repr(C)]
@@ -252,11 +300,6 @@ pub struct SpriteBatch {
     // colored: bool,
 }
 
-#[derive(Clone, ShaderType)]
-struct SimpleMaterialUniforms {
-    t: f32,
-}
-
 #[allow(clippy::too_many_arguments)]
 pub fn queue_sprites(
     mut commands: Commands,
@@ -271,8 +314,13 @@ pub fn queue_sprites(
     mut pipeline_cache: ResMut<PipelineCache>,
     msaa: Res<Msaa>,
     mut extracted_sprites: ResMut<ExtractedSprites>,
+    stats: Res<SimpleMesh2dStats>,
+    spans: Res<TraceSpans>,
     mut views: Query<(&VisibleEntities, &mut RenderPhase<Transparent2d>)>,
 ) {
+    let _span = spans.enter("queue_sprites");
+    let start = Instant::now();
+
     if let Some(view_binding) = view_uniforms.uniforms.binding() {
         let sprite_meta = &mut sprite_meta;
 
@@ -346,6 +394,8 @@ pub fn queue_sprites(
                         position: positions[i],
                         uv: QUAD_UVS[i].into(),
                         t: extracted_sprite.t,
+                        alpha: extracted_sprite.alpha,
+                        highlight: extracted_sprite.highlight,
                     });
                 }
                 let item_start = index;
@@ -368,6 +418,10 @@ pub fn queue_sprites(
         //     .colored_vertices
         //     .write_buffer(&render_device, &render_queue);
     }
+
+    stats
+        .queue_time
+        .store(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
 }
 
 pub type DrawSprite = (
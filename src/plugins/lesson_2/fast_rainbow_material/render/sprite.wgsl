@@ -8,6 +8,8 @@ var<uniform> view: View;
 struct VertexOutput {
     @location(0) uv: vec2<f32>,
     @location(1) t: f32,
+    @location(2) alpha: f32,
+    @location(3) highlight: f32,
     @builtin(position) position: vec4<f32>,
 };
 
@@ -16,11 +18,15 @@ fn vertex(
     @location(0) vertex_position: vec3<f32>,
     @location(1) vertex_uv: vec2<f32>,
     @location(2) t: f32,
+    @location(3) alpha: f32,
+    @location(4) highlight: f32,
 ) -> VertexOutput {
     var out: VertexOutput;
     out.uv = vertex_uv;
     out.position = view.view_proj * vec4<f32>(vertex_position, 1.0);
     out.t = t;
+    out.alpha = alpha;
+    out.highlight = highlight;
 
     return out;
 }
@@ -42,9 +48,13 @@ fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
     output_color[1] = pow(abs(sin(t + PI * 0.33333)), 2.2);
     output_color[2] = pow(abs(sin(t + PI * 0.666666)), 2.2);
 
-    var d = 1.0 - distance(vec2<f32>(0.5, 0.5), in.uv);
-    d = round(d);
-    output_color[3] = d;
+    var dist = distance(vec2<f32>(0.5, 0.5), in.uv);
+    var d = round(1.0 - dist);
+
+    // White rim on the outer part of the circle
+    var rim = in.highlight * step(0.38, dist);
+    output_color = mix(output_color, vec4<f32>(1.0, 1.0, 1.0, 1.0), rim);
+    output_color[3] = d * max(in.alpha, rim);
 
     return output_color;
 }
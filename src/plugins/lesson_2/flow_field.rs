@@ -0,0 +1,275 @@
+use std::f32::consts::PI;
+
+use bevy::{prelude::*, sprite::Anchor};
+use rand::seq::SliceRandom;
+use rand_pcg::Pcg64;
+
+use super::{
+    bevy_radial_physics::{Bounds, Force, PhysicsSystem},
+    pool::Pooled,
+};
+
+//
+//
+// Plugin
+
+pub struct FlowFieldPlugin;
+
+impl Plugin for FlowFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FlowField>()
+            .add_system(input_system)
+            .add_system(flow_system.before(PhysicsSystem::Movement))
+            .add_system(arrows_system.after(flow_system));
+    }
+}
+
+//
+//
+// Components
+
+#[derive(Component)]
+struct FlowArrow;
+
+//
+//
+// Resources
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FlowMode {
+    /// Noise value read as an angle
+    Perlin,
+    /// Rotated gradient of the noise, swirls without sinks or sources
+    Curl,
+}
+
+pub struct FlowField {
+    pub enabled: bool,
+    pub show_arrows: bool,
+    pub mode: FlowMode,
+    /// Noise frequency, features are roughly `1 / scale` pixels wide
+    pub scale: f32,
+    /// Acceleration in pixels per second squared
+    pub strength: f32,
+    /// How fast the field changes over time
+    pub speed: f32,
+    pub time: f32,
+    noise: Perlin,
+}
+
+impl Default for FlowField {
+    fn default() -> Self {
+        FlowField {
+            enabled: false,
+            show_arrows: false,
+            mode: FlowMode::Curl,
+            scale: 0.004,
+            strength: 300.0,
+            speed: 0.2,
+            time: 0.0,
+            noise: Perlin::new(0),
+        }
+    }
+}
+
+impl FlowField {
+    /// Direction of the flow at `pos`, at most one unit long
+    pub fn sample(&self, pos: Vec2) -> Vec2 {
+        let p = pos * self.scale;
+        let z = self.time * self.speed;
+
+        match self.mode {
+            FlowMode::Perlin => {
+                let a = self.noise.get(p.x, p.y, z) * PI * 2.0;
+                Vec2::new(a.cos(), a.sin())
+            }
+            FlowMode::Curl => {
+                const E: f32 = 0.01;
+                let dx =
+                    (self.noise.get(p.x + E, p.y, z) - self.noise.get(p.x - E, p.y, z)) / (2.0 * E);
+                let dy =
+                    (self.noise.get(p.x, p.y + E, z) - self.noise.get(p.x, p.y - E, z)) / (2.0 * E);
+                Vec2::new(dy, -dx).clamp_length_max(1.0)
+            }
+        }
+    }
+}
+
+//
+//
+// Systems
+
+fn input_system(keys: Res<Input<KeyCode>>, mut field: ResMut<FlowField>) {
+    if keys.just_pressed(KeyCode::F) {
+        field.enabled = !field.enabled;
+    }
+
+    if keys.just_pressed(KeyCode::G) {
+        field.show_arrows = !field.show_arrows;
+    }
+
+    if keys.just_pressed(KeyCode::H) {
+        field.mode = match field.mode {
+            FlowMode::Perlin => FlowMode::Curl,
+            FlowMode::Curl => FlowMode::Perlin,
+        };
+    }
+}
+
+fn flow_system(
+    time: Res<Time>,
+    mut field: ResMut<FlowField>,
+    mut query: Query<(&Transform, &mut Force), Without<Pooled>>,
+) {
+    if !field.enabled {
+        return;
+    }
+
+    let dt = time.delta().as_secs_f32();
+    field.time += dt;
+
+    query.for_each_mut(|(trns, mut frc)| {
+        let push = field.sample(trns.translation.truncate()) * field.strength * dt;
+        frc.velo += push.extend(0.0);
+    });
+}
+
+fn arrows_system(
+    field: Res<FlowField>,
+    bounds: Res<Bounds>,
+    mut arrows: Query<(Entity, &mut Transform), With<FlowArrow>>,
+    mut commands: Commands,
+) {
+    const SPACING: f32 = 32.0;
+
+    // Laid out over the bounds, so a resized window gets a new grid
+    if !field.show_arrows || bounds.is_changed() {
+        arrows.for_each(|(ntt, _)| commands.entity(ntt).despawn());
+    }
+
+    if !field.show_arrows {
+        return;
+    }
+
+    if arrows.is_empty() || bounds.is_changed() {
+        let cells = (bounds.size() / SPACING).ceil();
+        for x in 0..cells.x as i32 {
+            for y in 0..cells.y as i32 {
+                let pos = bounds.min + (Vec2::new(x as f32, y as f32) + 0.5) * SPACING;
+
+                commands
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgba(1.0, 1.0, 1.0, 0.4),
+                            custom_size: Some(Vec2::new(1.0, 2.0)),
+                            anchor: Anchor::CenterLeft,
+                            ..default()
+                        },
+                        transform: Transform::from_translation(pos.extend(1.0)),
+                        ..default()
+                    })
+                    .insert(FlowArrow);
+            }
+        }
+        return;
+    }
+
+    arrows.for_each_mut(|(_, mut trns)| {
+        let dir = field.sample(trns.translation.truncate());
+        trns.rotation = Quat::from_rotation_z(dir.y.atan2(dir.x));
+        trns.scale = Vec3::new(dir.length() * SPACING * 0.8, 1.0, 1.0);
+    });
+}
+
+//
+//
+// Helpers
+
+/// Ken Perlin's improved noise, returns values roughly in -1..1
+struct Perlin {
+    perm: [u8; 512],
+}
+
+impl Perlin {
+    fn new(seed: u64) -> Self {
+        let mut table: Vec<u8> = (0..=255).collect();
+        table.shuffle(&mut Pcg64::new(seed as u128, 0));
+
+        let mut perm = [0; 512];
+        for (i, p) in perm.iter_mut().enumerate() {
+            *p = table[i & 255];
+        }
+
+        Perlin { perm }
+    }
+
+    fn get(&self, x: f32, y: f32, z: f32) -> f32 {
+        let xi = (x.floor() as i32 & 255) as usize;
+        let yi = (y.floor() as i32 & 255) as usize;
+        let zi = (z.floor() as i32 & 255) as usize;
+
+        let x = x - x.floor();
+        let y = y - y.floor();
+        let z = z - z.floor();
+
+        let u = fade(x);
+        let v = fade(y);
+        let w = fade(z);
+
+        let p = &self.perm;
+        let a = p[xi] as usize + yi;
+        let aa = p[a] as usize + zi;
+        let ab = p[a + 1] as usize + zi;
+        let b = p[xi + 1] as usize + yi;
+        let ba = p[b] as usize + zi;
+        let bb = p[b + 1] as usize + zi;
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, grad(p[aa], x, y, z), grad(p[ba], x - 1.0, y, z)),
+                lerp(
+                    u,
+                    grad(p[ab], x, y - 1.0, z),
+                    grad(p[bb], x - 1.0, y - 1.0, z),
+                ),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad(p[aa + 1], x, y, z - 1.0),
+                    grad(p[ba + 1], x - 1.0, y, z - 1.0),
+                ),
+                lerp(
+                    u,
+                    grad(p[ab + 1], x, y - 1.0, z - 1.0),
+                    grad(p[bb + 1], x - 1.0, y - 1.0, z - 1.0),
+                ),
+            ),
+        )
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+fn grad(hash: u8, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
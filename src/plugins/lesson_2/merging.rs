@@ -0,0 +1,123 @@
+use bevy::{prelude::*, utils::HashSet};
+
+use super::{
+    bevy_radial_physics::{CircleCollider, Collision, CollisionResponse, Force, PhysicsSystem},
+    size_and_lifetime::{DeathCause, Died, Health, LifetimeSystem},
+    soft_body::SoftBodyPoint,
+};
+
+//
+//
+// Plugin
+
+/// Agar style mode toggled with M, overlapping dots merge instead of bouncing
+pub struct MergePlugin;
+
+impl Plugin for MergePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MergeMode>()
+            .add_system(input_system.before(PhysicsSystem::Movement))
+            .add_system(
+                merge_system
+                    .label(LifetimeSystem::Deaths)
+                    .after(PhysicsSystem::Movement),
+            );
+    }
+}
+
+//
+//
+// Resources
+
+#[derive(Default)]
+pub struct MergeMode(pub bool);
+
+//
+//
+// Systems
+
+fn input_system(
+    keys: Res<Input<KeyCode>>,
+    mut mode: ResMut<MergeMode>,
+    mut response: ResMut<CollisionResponse>,
+) {
+    if keys.just_pressed(KeyCode::M) {
+        mode.0 = !mode.0;
+    }
+
+    let wanted = if mode.0 {
+        CollisionResponse::Overlap
+    } else {
+        CollisionResponse::Bounce
+    };
+    if *response != wanted {
+        *response = wanted;
+    }
+}
+
+/// The bigger dot takes the smaller one's area and momentum, mass goes with area.
+/// Soft bodies fall apart when they lose a point, so their points never merge
+fn merge_system(
+    mode: Res<MergeMode>,
+    mut collisions: EventReader<Collision>,
+    mut query: Query<
+        (&mut Transform, &mut Force, &CircleCollider, &mut Health),
+        Without<SoftBodyPoint>,
+    >,
+    mut deaths: EventWriter<Died>,
+) {
+    if !mode.0 {
+        return;
+    }
+
+    let mut absorbed = HashSet::new();
+
+    for collision in collisions.iter() {
+        if absorbed.contains(&collision.a) || absorbed.contains(&collision.b) {
+            continue;
+        }
+
+        let read = |ntt: Entity| {
+            query.get(ntt).ok().map(|(trns, frc, col, health)| {
+                (trns.translation, frc.velo, col.r * trns.scale.x, health.max)
+            })
+        };
+
+        let (a, b) = match (read(collision.a), read(collision.b)) {
+            (Some(a), Some(b)) => (a, b),
+            _ => continue,
+        };
+
+        let ((big_ntt, big), (small_ntt, small)) = if a.2 >= b.2 {
+            ((collision.a, a), (collision.b, b))
+        } else {
+            ((collision.b, b), (collision.a, a))
+        };
+
+        // Physics already moved them this frame, skip pairs that drifted apart
+        if big.0.distance(small.0) > big.2 + small.2 {
+            continue;
+        }
+
+        let big_mass = big.2 * big.2;
+        let small_mass = small.2 * small.2;
+        let mass = big_mass + small_mass;
+        let growth = mass.sqrt() / big.2;
+
+        if let Ok((mut trns, mut frc, _, mut health)) = query.get_mut(big_ntt) {
+            trns.translation = (big.0 * big_mass + small.0 * small_mass) / mass;
+            trns.scale *= growth;
+            frc.velo = (big.1 * big_mass + small.1 * small_mass) / mass;
+            health.value *= growth;
+            health.max *= growth;
+        }
+
+        deaths.send(Died {
+            entity: small_ntt,
+            position: small.0,
+            size: small.3,
+            cause: DeathCause::Absorbed,
+        });
+        absorbed.insert(small_ntt);
+    }
+}
@@ -0,0 +1,298 @@
+use bevy::{
+    prelude::*,
+    sprite::{Anchor, MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use super::{
+    bevy_radial_physics::{CircleCollider, PolygonCollider, SegmentCollider, Static, StaticShape},
+    camera::WorldCursor,
+    shapes::create_polygon,
+};
+
+//
+//
+// Plugin
+
+/// Shift+drag draws the current tool, Tab switches tools, right click deletes,
+/// polygons are built from Shift+clicks and closed with Enter
+pub struct ObstaclesPlugin;
+
+impl Plugin for ObstaclesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(init_system).add_system(edit_system);
+    }
+}
+
+//
+//
+// Components
+
+#[derive(Component)]
+pub struct Obstacle;
+
+#[derive(Component)]
+struct ObstaclePreview;
+
+//
+//
+// Resources
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ObstacleTool {
+    Wall,
+    Circle,
+    Polygon,
+}
+
+pub struct ObstacleEditor {
+    pub tool: ObstacleTool,
+    drag_start: Option<Vec2>,
+    polygon: Vec<Vec2>,
+    circle_mesh: Mesh2dHandle,
+    material: Handle<ColorMaterial>,
+}
+
+impl ObstacleEditor {
+    /// Back to walls, dropping whatever was being drawn
+    pub fn reset(&mut self) {
+        self.tool = ObstacleTool::Wall;
+        self.drag_start = None;
+        self.polygon.clear();
+    }
+}
+
+const OBSTACLE_COLOR: Color = Color::rgb(0.4, 0.4, 0.4);
+const WALL_THICKNESS: f32 = 8.0;
+/// Shorter drags are taken for clicks and draw nothing
+const MIN_DRAG: f32 = 4.0;
+
+//
+//
+// Systems
+
+fn init_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(ObstacleEditor {
+        tool: ObstacleTool::Wall,
+        drag_start: None,
+        polygon: Vec::new(),
+        circle_mesh: meshes.add(Mesh::from(shape::Circle::new(0.5))).into(),
+        material: materials.add(ColorMaterial::from(OBSTACLE_COLOR)),
+    });
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(1.0, 1.0, 1.0, 0.5),
+                custom_size: Some(Vec2::new(1.0, 2.0)),
+                anchor: Anchor::CenterLeft,
+                ..default()
+            },
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(ObstaclePreview);
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn edit_system(
+    world_cursor: Res<WorldCursor>,
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mut editor: ResMut<ObstacleEditor>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    obstacles: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&CircleCollider>,
+            Option<&SegmentCollider>,
+            Option<&PolygonCollider>,
+        ),
+        With<Obstacle>,
+    >,
+    mut preview: Query<
+        (&mut Transform, &mut Visibility),
+        (With<ObstaclePreview>, Without<Obstacle>),
+    >,
+    mut commands: Commands,
+) {
+    let cursor = world_cursor.0;
+
+    if keys.just_pressed(KeyCode::Tab) {
+        editor.tool = match editor.tool {
+            ObstacleTool::Wall => ObstacleTool::Circle,
+            ObstacleTool::Circle => ObstacleTool::Polygon,
+            ObstacleTool::Polygon => ObstacleTool::Wall,
+        };
+        editor.drag_start = None;
+        editor.polygon.clear();
+    }
+
+    // Delete
+    if buttons.just_pressed(MouseButton::Right) {
+        if let Some(cursor) = cursor {
+            for (ntt, trns, circle, segment, polygon) in obstacles.iter() {
+                if let Some(shape) = StaticShape::from_components(trns, circle, segment, polygon) {
+                    if shape.contact(cursor, 1.0).is_some() {
+                        commands.entity(ntt).despawn();
+                    }
+                }
+            }
+        }
+    }
+
+    // Draw
+    let shift = keys.pressed(KeyCode::LShift);
+
+    match editor.tool {
+        ObstacleTool::Wall | ObstacleTool::Circle => {
+            if shift && buttons.just_pressed(MouseButton::Left) {
+                editor.drag_start = cursor;
+            }
+
+            if buttons.just_released(MouseButton::Left) {
+                let drag = editor.drag_start.take().zip(cursor);
+                if let Some((start, end)) = drag.filter(|(a, b)| a.distance(*b) >= MIN_DRAG) {
+                    if editor.tool == ObstacleTool::Wall {
+                        spawn_wall(&mut commands, start, end);
+                    } else {
+                        spawn_circle(&mut commands, &editor, start, start.distance(end));
+                    }
+                }
+            }
+        }
+        ObstacleTool::Polygon => {
+            if shift && buttons.just_pressed(MouseButton::Left) {
+                if let Some(cursor) = cursor {
+                    editor.polygon.push(cursor);
+                }
+            }
+
+            if keys.just_pressed(KeyCode::Return) {
+                let hull = convex_hull(&editor.polygon);
+                editor.polygon.clear();
+
+                if hull.len() >= 3 {
+                    spawn_polygon(&mut commands, &mut meshes, &editor, &hull);
+                }
+            }
+        }
+    }
+
+    // Preview a line from the drag start or the last polygon point
+    let anchor = editor.drag_start.or_else(|| editor.polygon.last().copied());
+    preview.for_each_mut(|(mut trns, mut visibility)| {
+        if let (Some(anchor), Some(cursor)) = (anchor, cursor) {
+            let delta = cursor - anchor;
+            trns.translation = anchor.extend(1.0);
+            trns.rotation = Quat::from_rotation_z(delta.y.atan2(delta.x));
+            trns.scale = Vec3::new(delta.length(), 1.0, 1.0);
+            visibility.is_visible = true;
+        } else {
+            visibility.is_visible = false;
+        }
+    });
+}
+
+//
+//
+// Helpers
+
+fn spawn_wall(commands: &mut Commands, start: Vec2, end: Vec2) {
+    let delta = end - start;
+    let len = delta.length();
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: OBSTACLE_COLOR,
+                custom_size: Some(Vec2::new(len, WALL_THICKNESS)),
+                ..default()
+            },
+            transform: Transform {
+                translation: ((start + end) * 0.5).extend(0.0),
+                rotation: Quat::from_rotation_z(delta.y.atan2(delta.x)),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(SegmentCollider {
+            a: Vec2::new(len * -0.5, 0.0),
+            b: Vec2::new(len * 0.5, 0.0),
+            r: WALL_THICKNESS * 0.5,
+        })
+        .insert(Static)
+        .insert(Obstacle);
+}
+
+fn spawn_circle(commands: &mut Commands, editor: &ObstacleEditor, center: Vec2, r: f32) {
+    commands
+        .spawn_bundle(MaterialMesh2dBundle {
+            mesh: editor.circle_mesh.clone(),
+            material: editor.material.clone(),
+            transform: Transform {
+                translation: center.extend(0.0),
+                scale: Vec3::splat(r * 2.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(CircleCollider { r: 0.5 })
+        .insert(Static)
+        .insert(Obstacle);
+}
+
+fn spawn_polygon(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    editor: &ObstacleEditor,
+    points: &[Vec2],
+) {
+    let center = points.iter().fold(Vec2::ZERO, |sum, p| sum + *p) / points.len() as f32;
+    let points: Vec<Vec2> = points.iter().map(|p| *p - center).collect();
+
+    commands
+        .spawn_bundle(MaterialMesh2dBundle {
+            mesh: meshes.add(create_polygon(&points)).into(),
+            material: editor.material.clone(),
+            transform: Transform::from_translation(center.extend(0.0)),
+            ..default()
+        })
+        .insert(PolygonCollider { points })
+        .insert(Static)
+        .insert(Obstacle);
+}
+
+/// Andrew's monotone chain, returns the hull counter-clockwise
+fn convex_hull(points: &[Vec2]) -> Vec<Vec2> {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+
+    let mut hull: Vec<Vec2> = Vec::new();
+
+    // Lower half left to right, then upper half right to left
+    for _ in 0..2 {
+        let start = hull.len();
+        for p in points.iter() {
+            while hull.len() >= start + 2 {
+                let a = hull[hull.len() - 2];
+                let b = hull[hull.len() - 1];
+                if (b - a).perp_dot(*p - a) > 0.0 {
+                    break;
+                }
+                hull.pop();
+            }
+            hull.push(*p);
+        }
+
+        // The last point starts the other half
+        hull.pop();
+        points.reverse();
+    }
+
+    hull
+}
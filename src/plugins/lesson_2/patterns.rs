@@ -0,0 +1,202 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+
+use super::{camera::WorldCursor, pool::DotPool, spawn_dot};
+
+//
+//
+// Plugin
+
+/// Number keys lay out fixed patterns of dots at the cursor, with Shift at the origin.
+/// They use no randomness, so the same key always gives the same start
+pub struct PatternsPlugin;
+
+impl Plugin for PatternsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(input_system);
+    }
+}
+
+//
+//
+// Systems
+
+fn input_system(
+    keys: Res<Input<KeyCode>>,
+    world_cursor: Res<WorldCursor>,
+    mut pool: ResMut<DotPool>,
+    mut commands: Commands,
+) {
+    let center = if keys.pressed(KeyCode::LShift) {
+        Vec2::ZERO
+    } else {
+        match world_cursor.0 {
+            Some(pos) => pos,
+            None => return,
+        }
+    };
+
+    if keys.just_pressed(KeyCode::Key1) {
+        spawn_burst(&mut commands, &mut pool, center, 256, 8.0, 300.0);
+    }
+    if keys.just_pressed(KeyCode::Key2) {
+        spawn_spiral(&mut commands, &mut pool, center, 512, 8.0, 24.0);
+    }
+    if keys.just_pressed(KeyCode::Key3) {
+        spawn_hex_grid(&mut commands, &mut pool, center, 32, 32, 8.0);
+    }
+    if keys.just_pressed(KeyCode::Key4) {
+        spawn_ring(&mut commands, &mut pool, center, 128, 8.0, 200.0, 150.0);
+    }
+    if keys.just_pressed(KeyCode::Key5) {
+        spawn_billiards(&mut commands, &mut pool, center, 5, 24.0, 800.0);
+    }
+}
+
+//
+//
+// Builders
+
+/// Dots side by side on a small circle, all flying outwards at `speed`
+pub fn spawn_burst(
+    commands: &mut Commands,
+    pool: &mut DotPool,
+    center: Vec2,
+    count: usize,
+    size: f32,
+    speed: f32,
+) -> Vec<Entity> {
+    let r = size * count as f32 / TAU;
+    let dots = (0..count).map(|i| {
+        let dir = unit(i as f32 / count as f32 * TAU);
+        (center + dir * r, dir * speed)
+    });
+
+    spawn_dots(commands, pool, size, dots.collect())
+}
+
+/// Archimedean spiral with arms `spacing` apart, dots are a diameter apart along the arm
+pub fn spawn_spiral(
+    commands: &mut Commands,
+    pool: &mut DotPool,
+    center: Vec2,
+    count: usize,
+    size: f32,
+    spacing: f32,
+) -> Vec<Entity> {
+    let mut points = Vec::with_capacity(count);
+
+    // Start one turn out so the first dots do not overlap
+    let mut angle = TAU;
+    for _ in 0..count {
+        let r = spacing * angle / TAU;
+        points.push((center + unit(angle) * r, Vec2::ZERO));
+        angle += size / r;
+    }
+
+    spawn_dots(commands, pool, size, points)
+}
+
+/// Densest packing of `cols` by `rows` touching dots at rest, every other row shifted
+pub fn spawn_hex_grid(
+    commands: &mut Commands,
+    pool: &mut DotPool,
+    center: Vec2,
+    cols: usize,
+    rows: usize,
+    size: f32,
+) -> Vec<Entity> {
+    if cols == 0 || rows == 0 {
+        return Vec::new();
+    }
+
+    let row_height = size * 3f32.sqrt() * 0.5;
+    let origin = center - Vec2::new(cols as f32 * size, (rows - 1) as f32 * row_height) * 0.5;
+
+    let dots = (0..rows).flat_map(move |row| {
+        let shift = if row % 2 == 0 { 0.0 } else { size * 0.5 };
+        (0..cols).map(move |col| {
+            let pos = origin + Vec2::new(col as f32 * size + shift, row as f32 * row_height);
+            (pos, Vec2::ZERO)
+        })
+    });
+
+    spawn_dots(commands, pool, size, dots.collect())
+}
+
+/// Dots on a circle moving counter-clockwise along it
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_ring(
+    commands: &mut Commands,
+    pool: &mut DotPool,
+    center: Vec2,
+    count: usize,
+    size: f32,
+    radius: f32,
+    speed: f32,
+) -> Vec<Entity> {
+    let dots = (0..count).map(|i| {
+        let dir = unit(i as f32 / count as f32 * TAU);
+        (center + dir * radius, dir.perp() * speed)
+    });
+
+    spawn_dots(commands, pool, size, dots.collect())
+}
+
+/// Triangle rack pointing left with its tip at `center`, and a cue ball shot at the tip
+pub fn spawn_billiards(
+    commands: &mut Commands,
+    pool: &mut DotPool,
+    center: Vec2,
+    rows: usize,
+    size: f32,
+    cue_speed: f32,
+) -> Vec<Entity> {
+    // A hair of space keeps the rack from starting out squashed
+    let gap = size + 1.0;
+    let row_width = gap * 3f32.sqrt() * 0.5;
+
+    let mut dots = vec![(center - Vec2::new(gap * 8.0, 0.0), Vec2::X * cue_speed)];
+    for row in 0..rows {
+        for i in 0..=row {
+            let offset = Vec2::new(row as f32 * row_width, (i as f32 - row as f32 * 0.5) * gap);
+            dots.push((center + offset, Vec2::ZERO));
+        }
+    }
+
+    spawn_dots(commands, pool, size, dots)
+}
+
+//
+//
+// Helpers
+
+fn unit(angle: f32) -> Vec2 {
+    Vec2::new(angle.cos(), angle.sin())
+}
+
+/// Colors run through the rainbow in spawn order, stops early when the pool is full
+fn spawn_dots(
+    commands: &mut Commands,
+    pool: &mut DotPool,
+    size: f32,
+    dots: Vec<(Vec2, Vec2)>,
+) -> Vec<Entity> {
+    let count = dots.len() as f32;
+
+    dots.into_iter()
+        .enumerate()
+        .map_while(|(i, (pos, velo))| {
+            let color_offset = i as f32 / count * PI;
+            spawn_dot(
+                commands,
+                pool,
+                pos.extend(0.0),
+                size,
+                velo.extend(0.0),
+                color_offset,
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,277 @@
+use std::{
+    fmt::Write,
+    fs,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    app::AppExit,
+    diagnostic::{DiagnosticId, Diagnostics, EntityCountDiagnosticsPlugin},
+    prelude::*,
+};
+use serde::Serialize;
+
+use super::perf_log::{PerfLogDiagnostics, PerfLogPlugin};
+
+//
+//
+// Plugin
+
+/// F10 starts and stops a perf session, stopping or closing the app writes one sample
+/// per frame to a CSV or JSON file in `measurements/`. F11 switches between the two
+pub struct PerfExportPlugin;
+
+impl Plugin for PerfExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PerfExport>()
+            .add_system_to_stage(CoreStage::Last, export_system);
+    }
+}
+
+pub const MEASUREMENTS_DIR: &str = "measurements";
+
+//
+//
+// Resources
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PerfExportFormat {
+    Csv,
+    Json,
+}
+
+pub struct PerfExport {
+    pub format: PerfExportFormat,
+    /// Written to the header and used in the file name
+    pub lesson: String,
+    /// Entity counts the experiment is meant to reach, written to the header
+    pub entity_targets: Vec<usize>,
+    session: Option<PerfSession>,
+}
+
+impl Default for PerfExport {
+    fn default() -> Self {
+        PerfExport::new("unnamed", Vec::new())
+    }
+}
+
+impl PerfExport {
+    pub fn new(lesson: &str, entity_targets: Vec<usize>) -> Self {
+        PerfExport {
+            format: PerfExportFormat::Csv,
+            lesson: lesson.to_string(),
+            entity_targets,
+            session: None,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.session.is_some()
+    }
+}
+
+#[derive(Serialize)]
+struct PerfSessionMetadata {
+    lesson: String,
+    present_mode: String,
+    /// `None` outside of a git checkout
+    git_commit: Option<String>,
+    entity_targets: Vec<usize>,
+    /// Unix time in seconds
+    started: u64,
+}
+
+#[derive(Serialize)]
+struct PerfSession {
+    metadata: PerfSessionMetadata,
+    columns: Vec<String>,
+    /// One row per frame, `None` for diagnostics without a value yet
+    samples: Vec<Vec<Option<f64>>>,
+    /// Custom diagnostics, in column order
+    #[serde(skip)]
+    ids: Vec<DiagnosticId>,
+    /// Seconds since startup
+    #[serde(skip)]
+    start: f64,
+}
+
+//
+//
+// Systems
+
+/// Runs last so every diagnostic has its measurement for this frame
+fn export_system(
+    keys: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    windows: Res<Windows>,
+    diagnostics: Res<Diagnostics>,
+    lines: Res<PerfLogDiagnostics>,
+    mut exits: EventReader<AppExit>,
+    mut export: ResMut<PerfExport>,
+) {
+    if keys.just_pressed(KeyCode::F11) {
+        export.format = match export.format {
+            PerfExportFormat::Csv => PerfExportFormat::Json,
+            PerfExportFormat::Json => PerfExportFormat::Csv,
+        };
+        info!("perf sessions export as {:?}", export.format);
+    }
+
+    // The app stops after this frame, the session would be lost
+    if exits.iter().count() > 0 {
+        if let Some(session) = export.session.take() {
+            finish(&session, export.format);
+        }
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::F10) {
+        match export.session.take() {
+            Some(session) => finish(&session, export.format),
+            None => {
+                let present_mode = windows
+                    .get_primary()
+                    .map(|window| format!("{:?}", window.present_mode()))
+                    .unwrap_or_default();
+
+                // Frame time and entities have their own columns
+                let ids: Vec<DiagnosticId> = lines
+                    .0
+                    .iter()
+                    .map(|(id, _)| *id)
+                    .filter(|id| {
+                        *id != PerfLogPlugin::FRAME_TIME
+                            && *id != EntityCountDiagnosticsPlugin::ENTITY_COUNT
+                    })
+                    .filter(|id| diagnostics.get(*id).is_some())
+                    .collect();
+
+                let mut columns: Vec<String> = ["time (s)", "frame time (ms)", "entities"]
+                    .iter()
+                    .map(|column| column.to_string())
+                    .collect();
+                for id in ids.iter() {
+                    let d = diagnostics.get(*id).unwrap();
+                    if d.suffix.is_empty() {
+                        columns.push(d.name.to_string());
+                    } else {
+                        columns.push(format!("{} ({})", d.name, d.suffix));
+                    }
+                }
+
+                export.session = Some(PerfSession {
+                    metadata: PerfSessionMetadata {
+                        lesson: export.lesson.clone(),
+                        present_mode,
+                        git_commit: git_commit(),
+                        entity_targets: export.entity_targets.clone(),
+                        started: SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map_or(0, |since| since.as_secs()),
+                    },
+                    columns,
+                    samples: Vec::new(),
+                    ids,
+                    start: time.seconds_since_startup(),
+                });
+                info!("perf session started");
+            }
+        }
+    }
+
+    let session = match &mut export.session {
+        Some(session) => session,
+        None => return,
+    };
+
+    let value = |id: DiagnosticId| diagnostics.get(id).and_then(|d| d.value());
+
+    let mut sample = vec![
+        Some(time.seconds_since_startup() - session.start),
+        Some(time.delta_seconds_f64() * 1000.0),
+        value(EntityCountDiagnosticsPlugin::ENTITY_COUNT),
+    ];
+    sample.extend(session.ids.iter().map(|id| value(*id)));
+    session.samples.push(sample);
+}
+
+//
+//
+// Helpers
+
+fn finish(session: &PerfSession, format: PerfExportFormat) {
+    let path = format!(
+        "{}/{}_{}.{}",
+        MEASUREMENTS_DIR,
+        session.metadata.lesson,
+        session.metadata.started,
+        match format {
+            PerfExportFormat::Csv => "csv",
+            PerfExportFormat::Json => "json",
+        }
+    );
+
+    match save(session, format, &path) {
+        Ok(()) => info!("wrote {} frames to {}", session.samples.len(), path),
+        Err(err) => error!("could not write {}: {:?}", path, err),
+    }
+}
+
+fn save(session: &PerfSession, format: PerfExportFormat, path: &str) -> anyhow::Result<()> {
+    let contents = match format {
+        PerfExportFormat::Csv => to_csv(session),
+        PerfExportFormat::Json => serde_json::to_string_pretty(session)?,
+    };
+
+    fs::create_dir_all(MEASUREMENTS_DIR)?;
+    fs::write(path, contents)?;
+
+    Ok(())
+}
+
+/// Metadata goes into `#` comment lines above the column names
+fn to_csv(session: &PerfSession) -> String {
+    let metadata = &session.metadata;
+    let targets: Vec<String> = metadata
+        .entity_targets
+        .iter()
+        .map(|target| target.to_string())
+        .collect();
+
+    let mut csv = String::new();
+    writeln!(csv, "# lesson: {}", metadata.lesson).unwrap();
+    writeln!(csv, "# present mode: {}", metadata.present_mode).unwrap();
+    writeln!(
+        csv,
+        "# git commit: {}",
+        metadata.git_commit.as_deref().unwrap_or("unknown")
+    )
+    .unwrap();
+    writeln!(csv, "# entity targets: {}", targets.join(" ")).unwrap();
+    writeln!(csv, "# started: {}", metadata.started).unwrap();
+    writeln!(csv, "{}", session.columns.join(",")).unwrap();
+
+    for sample in session.samples.iter() {
+        let row: Vec<String> = sample
+            .iter()
+            .map(|value| value.map(|v| v.to_string()).unwrap_or_default())
+            .collect();
+        writeln!(csv, "{}", row.join(",")).unwrap();
+    }
+
+    csv
+}
+
+/// Commit the build was made from, as far as the working directory knows
+fn git_commit() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
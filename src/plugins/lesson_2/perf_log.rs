@@ -1,23 +1,79 @@
+use std::{collections::VecDeque, fmt::Write};
+
 use bevy::{
-    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    diagnostic::{
+        Diagnostic, DiagnosticId, Diagnostics, EntityCountDiagnosticsPlugin,
+        FrameTimeDiagnosticsPlugin,
+    },
     prelude::*,
 };
 
+use super::{perf_export::PerfExportPlugin, trace_export::TraceExportPlugin};
+
 //
 //
 // Plugin
 
+/// Grave toggles an overlay with one line for every diagnostic in `PerfLogDiagnostics`,
+/// a graph of the last few seconds of frame times and a histogram of them.
+/// Also adds `PerfExportPlugin` to write the same numbers to a file
+/// and `TraceExportPlugin` for a trace of the expensive systems
 pub struct PerfLogPlugin;
 
+impl PerfLogPlugin {
+    /// Frame time in milliseconds with a long history, for the spread
+    pub const FRAME_TIME: DiagnosticId =
+        DiagnosticId::from_u128(163942394929390137848641464737298918883);
+}
+
 impl Plugin for PerfLogPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(FrameTimeDiagnosticsPlugin)
+            .add_plugin(EntityCountDiagnosticsPlugin)
+            .add_plugin(PerfExportPlugin)
+            .add_plugin(TraceExportPlugin)
+            .init_resource::<PerfLogDiagnostics>()
+            .init_resource::<FrameTimes>()
             .add_startup_system(init_system)
+            .add_system(frame_time_system)
             .add_system(log_system)
+            .add_system(graph_system)
             .add_system(log_visibility_system);
+
+        // On top, even when other plugins were added first
+        app.world.resource_mut::<PerfLogDiagnostics>().0.splice(
+            0..0,
+            [
+                (FrameTimeDiagnosticsPlugin::FPS, PerfLogStat::Average),
+                (Self::FRAME_TIME, PerfLogStat::Spread),
+                (
+                    EntityCountDiagnosticsPlugin::ENTITY_COUNT,
+                    PerfLogStat::Value,
+                ),
+            ],
+        );
     }
 }
 
+/// Frames the frame time spread is taken over
+const FRAME_HISTORY: usize = 600;
+
+/// Seconds of frame times in the graph and the histogram
+const GRAPH_SECONDS: f64 = 5.0;
+/// Every column shows the slowest frame in its slice of time, so spikes stand out
+const GRAPH_COLUMNS: usize = 100;
+const GRAPH_SIZE: Vec2 = Vec2::new(300.0, 100.0);
+/// Frame time of a full height column
+const GRAPH_MAX_MS: f32 = 50.0;
+
+const HISTOGRAM_BINS: usize = 50;
+/// Width of one bin, the last bin takes everything slower
+const HISTOGRAM_BIN_MS: f32 = 1.0;
+const HISTOGRAM_SIZE: Vec2 = Vec2::new(300.0, 80.0);
+
+/// Lowest free spot for panels other plugins stack above the frame time panels
+pub const PANEL_STACK_BOTTOM: f32 = GRAPH_SIZE.y + HISTOGRAM_SIZE.y + 48.0;
+
 //
 //
 // Components
@@ -25,17 +81,50 @@ impl Plugin for PerfLogPlugin {
 #[derive(Component)]
 struct PerfLogUI;
 
+#[derive(Component)]
+struct GraphBar(usize);
+
+#[derive(Component)]
+struct HistogramBar(usize);
+
 //
 //
 // Resources
 
 struct PerfLogEnabled(bool);
 
+/// How a diagnostic is shown in the overlay
+#[derive(Clone, Copy)]
+pub enum PerfLogStat {
+    /// Latest measurement
+    Value,
+    /// Average over the history
+    Average,
+    /// Min, average, max and 99th percentile over the history
+    Spread,
+}
+
+/// Diagnostics listed in the overlay, other plugins push their ids here
+#[derive(Default)]
+pub struct PerfLogDiagnostics(pub Vec<(DiagnosticId, PerfLogStat)>);
+
+/// Seconds since startup and frame time in milliseconds of the last `GRAPH_SECONDS`
+#[derive(Default)]
+struct FrameTimes(VecDeque<(f64, f32)>);
+
 //
 //
 // Systems
 
-fn init_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn init_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut diagnostics: ResMut<Diagnostics>,
+) {
+    diagnostics.add(
+        Diagnostic::new(PerfLogPlugin::FRAME_TIME, "frame time", FRAME_HISTORY).with_suffix("ms"),
+    );
+
     let font = asset_server.load("fonts/roboto_condensed/RobotoCondensed-Regular.ttf");
 
     let regular_text = TextStyle {
@@ -58,9 +147,36 @@ fn init_system(mut commands: Commands, asset_server: Res<AssetServer>) {
         )
         .insert(PerfLogUI);
 
+    spawn_bar_panel(&mut commands, 16.0, GRAPH_SIZE, GRAPH_COLUMNS, GraphBar);
+    spawn_bar_panel(
+        &mut commands,
+        GRAPH_SIZE.y + 32.0,
+        HISTOGRAM_SIZE,
+        HISTOGRAM_BINS,
+        HistogramBar,
+    );
+
     commands.insert_resource(PerfLogEnabled(false));
 }
 
+fn frame_time_system(
+    time: Res<Time>,
+    mut diagnostics: ResMut<Diagnostics>,
+    mut frame_times: ResMut<FrameTimes>,
+) {
+    let ms = time.delta_seconds_f64() * 1000.0;
+    diagnostics.add_measurement(PerfLogPlugin::FRAME_TIME, || ms);
+
+    let now = time.seconds_since_startup();
+    frame_times.0.push_back((now, ms as f32));
+    while let Some((t, _)) = frame_times.0.front() {
+        if now - t <= GRAPH_SECONDS {
+            break;
+        }
+        frame_times.0.pop_front();
+    }
+}
+
 fn log_visibility_system(
     input: Res<Input<KeyCode>>,
     mut do_log: ResMut<PerfLogEnabled>,
@@ -75,15 +191,161 @@ fn log_visibility_system(
     });
 }
 
-fn log_system(diag: Res<Diagnostics>, mut q: Query<&mut Text, With<PerfLogUI>>) {
-    let mut fps = 0.0;
-    if let Some(fps_diag) = diag.get(FrameTimeDiagnosticsPlugin::FPS) {
-        if let Some(fps_avg) = fps_diag.average() {
-            fps = fps_avg;
+fn log_system(
+    diag: Res<Diagnostics>,
+    lines: Res<PerfLogDiagnostics>,
+    mut q: Query<&mut Text, With<PerfLogUI>>,
+) {
+    let mut log = String::new();
+    for (id, stat) in lines.0.iter() {
+        let d = match diag.get(*id) {
+            Some(d) => d,
+            None => continue,
+        };
+
+        let value = match stat {
+            PerfLogStat::Value => d.value().map(|v| format_value(v, &d.suffix)),
+            PerfLogStat::Average => d.average().map(|v| format_value(v, &d.suffix)),
+            PerfLogStat::Spread => spread(d).map(|[min, avg, max, p99]| {
+                format!(
+                    "{} min, {} avg, {} max, {} p99",
+                    format_value(min, &d.suffix),
+                    format_value(avg, &d.suffix),
+                    format_value(max, &d.suffix),
+                    format_value(p99, &d.suffix),
+                )
+            }),
+        };
+
+        if let Some(value) = value {
+            if !log.is_empty() {
+                log.push('\n');
+            }
+            write!(log, "{}: {}", d.name, value).unwrap();
         }
     }
 
     q.for_each_mut(|mut text| {
-        text.sections[0].value = format!("fps: {:.0}", fps);
+        text.sections[0].value = log.clone();
     });
 }
+
+fn graph_system(
+    time: Res<Time>,
+    enabled: Res<PerfLogEnabled>,
+    frame_times: Res<FrameTimes>,
+    mut graph: Query<(&GraphBar, &mut Style, &mut UiColor)>,
+    mut histogram: Query<(&HistogramBar, &mut Style, &mut UiColor), Without<GraphBar>>,
+) {
+    if !enabled.0 {
+        return;
+    }
+
+    let now = time.seconds_since_startup();
+
+    // Newest frames on the right
+    let mut columns = [0.0f32; GRAPH_COLUMNS];
+    let mut bins = [0usize; HISTOGRAM_BINS];
+    for (t, ms) in frame_times.0.iter() {
+        let age = ((now - t) / GRAPH_SECONDS * GRAPH_COLUMNS as f64) as usize;
+        let column = GRAPH_COLUMNS - 1 - age.min(GRAPH_COLUMNS - 1);
+        columns[column] = columns[column].max(*ms);
+
+        let bin = ((ms / HISTOGRAM_BIN_MS) as usize).min(HISTOGRAM_BINS - 1);
+        bins[bin] += 1;
+    }
+
+    for (bar, mut style, mut color) in graph.iter_mut() {
+        let ms = columns[bar.0];
+        style.size.height = Val::Px((ms / GRAPH_MAX_MS).min(1.0) * GRAPH_SIZE.y);
+        color.0 = frame_time_color(ms);
+    }
+
+    // Log scale, a single slow frame still gets a visible bar
+    let max_count = bins.iter().copied().max().unwrap_or(0).max(1);
+    for (bar, mut style, mut color) in histogram.iter_mut() {
+        let share = (1.0 + bins[bar.0] as f32).ln() / (1.0 + max_count as f32).ln();
+        style.size.height = Val::Px(share * HISTOGRAM_SIZE.y);
+        color.0 = frame_time_color(bar.0 as f32 * HISTOGRAM_BIN_MS);
+    }
+}
+
+//
+//
+// Helpers
+
+/// Dark box in the bottom right corner with a row of bars growing from its bottom edge.
+/// It belongs to the overlay, so Grave toggles it too
+pub fn spawn_bar_panel<C: Component>(
+    commands: &mut Commands,
+    bottom: f32,
+    size: Vec2,
+    bars: usize,
+    marker: impl Fn(usize) -> C,
+) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(bottom),
+                    right: Val::Px(16.0),
+                    ..default()
+                },
+                size: Size::new(Val::Px(size.x), Val::Px(size.y)),
+                align_items: AlignItems::FlexStart,
+                ..default()
+            },
+            color: UiColor(Color::rgba(0.0, 0.0, 0.0, 0.5)),
+            ..default()
+        })
+        .insert(PerfLogUI)
+        .with_children(|panel| {
+            for i in 0..bars {
+                panel
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(size.x / bars as f32), Val::Px(0.0)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .insert(PerfLogUI)
+                    .insert(marker(i));
+            }
+        });
+}
+
+/// Green up to 60 fps, yellow up to 30 fps, red below
+fn frame_time_color(ms: f32) -> Color {
+    if ms <= 1000.0 / 60.0 {
+        Color::rgb(0.3, 0.9, 0.3)
+    } else if ms <= 1000.0 / 30.0 {
+        Color::rgb(0.9, 0.8, 0.2)
+    } else {
+        Color::rgb(0.9, 0.2, 0.2)
+    }
+}
+
+/// Counts are whole numbers, anything with a unit gets two decimals
+fn format_value(value: f64, suffix: &str) -> String {
+    if suffix.is_empty() {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.2}{}", value, suffix)
+    }
+}
+
+/// Min, average, max and 99th percentile of the whole history
+fn spread(diagnostic: &Diagnostic) -> Option<[f64; 4]> {
+    let mut values: Vec<f64> = diagnostic.measurements().map(|m| m.value).collect();
+    if values.is_empty() {
+        return None;
+    }
+
+    values.sort_unstable_by(|a, b| a.total_cmp(b));
+    let avg = values.iter().sum::<f64>() / values.len() as f64;
+    let p99 = values[(values.len() * 99).div_ceil(100) - 1];
+
+    Some([values[0], avg, values[values.len() - 1], p99])
+}
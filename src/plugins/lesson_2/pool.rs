@@ -0,0 +1,228 @@
+use bevy::{prelude::*, utils::HashSet};
+
+use super::{
+    bevy_radial_physics::{CircleCollider, PhysicsSystem},
+    ecology::Species,
+    fast_rainbow_material::SimpleMesh2d,
+    size_and_lifetime::{
+        Armor, DeathCause, Died, Health, Lifetime, LifetimeCurves, LifetimeSystem, SplitOnDeath,
+    },
+    soft_body::SoftBodyPoint,
+    steering::Boid,
+};
+
+//
+//
+// Plugin
+
+/// Dead dots are hidden and reused by `spawn_dot` instead of despawned,
+/// K cycles what happens when the population cap is reached
+pub struct PoolPlugin;
+
+impl Plugin for PoolPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DotPool>()
+            .add_system_to_stage(CoreStage::First, count_system)
+            .add_system(input_system)
+            .add_system(
+                evict_system
+                    .label(LifetimeSystem::Deaths)
+                    .before(PhysicsSystem::Movement),
+            )
+            .add_system_to_stage(CoreStage::PostUpdate, reap_system);
+    }
+}
+
+//
+//
+// Components
+
+/// Parked in the pool, every system that works on dots skips these.
+/// Sparse storage keeps parking and reusing from moving the other components around
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Pooled;
+
+//
+//
+// Resources
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EvictionPolicy {
+    /// `spawn_dot` returns `None` until dots die on their own
+    Refuse,
+    /// Removes the dots that lived the longest
+    Oldest,
+    /// Removes the dots with the smallest scale
+    Smallest,
+}
+
+pub struct DotPool {
+    /// Park dead dots for reuse, otherwise they are despawned
+    pub recycle: bool,
+    pub max_population: usize,
+    pub policy: EvictionPolicy,
+    free: Vec<Entity>,
+    /// Dots alive at the start of the frame plus the ones spawned since
+    live: usize,
+}
+
+impl Default for DotPool {
+    fn default() -> Self {
+        DotPool {
+            recycle: true,
+            max_population: 5000,
+            policy: EvictionPolicy::Oldest,
+            free: Vec::new(),
+            live: 0,
+        }
+    }
+}
+
+impl DotPool {
+    pub fn live(&self) -> usize {
+        self.live
+    }
+
+    pub fn pooled(&self) -> usize {
+        self.free.len()
+    }
+
+    /// How many dots can be spawned right now, only the refuse policy ever says no
+    pub fn room(&self) -> usize {
+        match self.policy {
+            EvictionPolicy::Refuse => self.max_population.saturating_sub(self.live),
+            _ => usize::MAX,
+        }
+    }
+
+    /// Kills every dot for a fresh start, they stop counting against the cap right away
+    pub fn clear<'a>(
+        &mut self,
+        dots: impl Iterator<Item = (Entity, &'a Transform, &'a Health)>,
+        deaths: &mut EventWriter<Died>,
+    ) {
+        for (ntt, trns, health) in dots {
+            deaths.send(Died {
+                entity: ntt,
+                position: trns.translation,
+                size: health.max,
+                cause: DeathCause::Cleared,
+            });
+            self.live = self.live.saturating_sub(1);
+        }
+    }
+
+    /// Forgets every dot, parked ones included, for a start from an empty world.
+    /// Settings go back to their defaults except the cap. The caller despawns the dots
+    pub fn reset(&mut self) {
+        *self = DotPool {
+            max_population: self.max_population,
+            ..default()
+        };
+    }
+
+    /// Hands out a parked entity if there is one, counts the dot as live either way
+    pub(super) fn take(&mut self) -> Option<Entity> {
+        self.live += 1;
+        self.free.pop()
+    }
+}
+
+//
+//
+// Systems
+
+/// Query filter for dots that are not parked
+pub type LiveDot = (With<CircleCollider>, With<SimpleMesh2d>, Without<Pooled>);
+
+fn count_system(mut pool: ResMut<DotPool>, dots: Query<(), LiveDot>) {
+    pool.live = dots.iter().count();
+}
+
+fn input_system(keys: Res<Input<KeyCode>>, mut pool: ResMut<DotPool>) {
+    if keys.just_pressed(KeyCode::K) {
+        pool.policy = match pool.policy {
+            EvictionPolicy::Refuse => EvictionPolicy::Oldest,
+            EvictionPolicy::Oldest => EvictionPolicy::Smallest,
+            EvictionPolicy::Smallest => EvictionPolicy::Refuse,
+        };
+        info!("population cap policy: {:?}", pool.policy);
+    }
+}
+
+fn evict_system(
+    pool: Res<DotPool>,
+    dots: Query<(Entity, &Transform, &Health, &Lifetime), LiveDot>,
+    mut deaths: EventWriter<Died>,
+) {
+    if pool.policy == EvictionPolicy::Refuse || pool.live <= pool.max_population {
+        return;
+    }
+
+    let mut candidates: Vec<(Entity, &Transform, &Health, f32)> = dots
+        .iter()
+        .map(|(ntt, trns, health, lifetime)| {
+            let priority = match pool.policy {
+                EvictionPolicy::Oldest => -lifetime.elapsed,
+                _ => trns.scale.x,
+            };
+            (ntt, trns, health, priority)
+        })
+        .collect();
+
+    let excess = candidates.len().saturating_sub(pool.max_population);
+    if excess == 0 {
+        return;
+    }
+
+    candidates.select_nth_unstable_by(excess - 1, |a, b| a.3.total_cmp(&b.3));
+
+    for (ntt, trns, health, _) in candidates.into_iter().take(excess) {
+        deaths.send(Died {
+            entity: ntt,
+            position: trns.translation,
+            size: health.max,
+            cause: DeathCause::Evicted,
+        });
+    }
+}
+
+/// Everything that kills a dot only sends `Died`, this is where it actually goes away.
+/// Runs in `PostUpdate`, after `LifetimeSystem::Split` had its look at the dead
+fn reap_system(
+    mut pool: ResMut<DotPool>,
+    mut deaths: EventReader<Died>,
+    dots: Query<Option<&Pooled>, (With<CircleCollider>, With<SimpleMesh2d>)>,
+    mut commands: Commands,
+) {
+    let mut reaped = HashSet::new();
+
+    for death in deaths.iter() {
+        if !reaped.insert(death.entity) {
+            continue;
+        }
+
+        match dots.get(death.entity) {
+            // Already parked
+            Ok(Some(_)) => {}
+            Ok(None) if pool.recycle => {
+                // Whatever the next spawn does not set again has to go
+                commands
+                    .entity(death.entity)
+                    .remove_bundle::<(
+                        LifetimeCurves,
+                        SplitOnDeath,
+                        Armor,
+                        Species,
+                        Boid,
+                        SoftBodyPoint,
+                    )>()
+                    .insert(Visibility { is_visible: false })
+                    .insert(Pooled);
+                pool.free.push(death.entity);
+            }
+            _ => commands.entity(death.entity).despawn(),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use super::spawner::{DotTemplate, Spawner};
+
+//
+//
+// Plugin
+
+/// Spawner settings read from `assets/presets/*.preset.ron`, reloaded when the files change
+pub struct PresetsPlugin;
+
+impl Plugin for PresetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<SpawnPreset>()
+            .init_asset_loader::<SpawnPresetLoader>()
+            .add_startup_system(init_system)
+            .add_system(apply_system);
+    }
+}
+
+const PRESETS: [&str; 4] = [
+    "presets/default.preset.ron",
+    "presets/dust.preset.ron",
+    "presets/boulders.preset.ron",
+    "presets/ghosts.preset.ron",
+];
+
+//
+//
+// Components
+
+/// Spawners with this get the template of the current preset
+#[derive(Component)]
+pub struct FollowsPreset;
+
+//
+//
+// Resources
+
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "9d5e0a12-0a3d-426f-9440-3cf3970c0592"]
+pub struct SpawnPreset {
+    pub name: String,
+    pub template: DotTemplate,
+}
+
+pub struct SpawnPresets {
+    pub handles: Vec<Handle<SpawnPreset>>,
+    pub current: usize,
+}
+
+impl SpawnPresets {
+    pub fn current(&self) -> Option<&Handle<SpawnPreset>> {
+        self.handles.get(self.current)
+    }
+
+    pub fn next(&mut self) {
+        if !self.handles.is_empty() {
+            self.current = (self.current + 1) % self.handles.len();
+        }
+    }
+}
+
+#[derive(Default)]
+struct SpawnPresetLoader;
+
+impl AssetLoader for SpawnPresetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let preset: SpawnPreset = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(preset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["preset.ron"]
+    }
+}
+
+//
+//
+// Systems
+
+fn init_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    if let Err(err) = asset_server.watch_for_changes() {
+        warn!("presets will not hot reload: {:?}", err);
+    }
+
+    commands.insert_resource(SpawnPresets {
+        handles: PRESETS
+            .iter()
+            .map(|path| asset_server.load(*path))
+            .collect(),
+        current: 0,
+    });
+}
+
+fn apply_system(
+    presets: Res<SpawnPresets>,
+    assets: Res<Assets<SpawnPreset>>,
+    mut events: EventReader<AssetEvent<SpawnPreset>>,
+    mut spawners: Query<&mut Spawner, With<FollowsPreset>>,
+) {
+    let handle = match presets.current() {
+        Some(handle) => handle,
+        None => return,
+    };
+
+    // Switched presets, or the current one finished loading or was edited
+    let reloaded = events.iter().any(|event| match event {
+        AssetEvent::Created { handle: h } | AssetEvent::Modified { handle: h } => h == handle,
+        AssetEvent::Removed { .. } => false,
+    });
+
+    if !presets.is_changed() && !reloaded {
+        return;
+    }
+
+    if let Some(preset) = assets.get(handle) {
+        info!("spawn preset: {}", preset.name);
+        for mut spawner in spawners.iter_mut() {
+            spawner.template = preset.template.clone();
+        }
+    }
+}
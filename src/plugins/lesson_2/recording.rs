@@ -0,0 +1,403 @@
+use std::{fs, mem, path::Path, time::Duration};
+
+use bevy::{
+    input::{
+        keyboard::KeyboardInput,
+        mouse::{MouseButtonInput, MouseScrollUnit, MouseWheel},
+        ButtonState, InputSystem,
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    bevy_radial_physics::CircleCollider,
+    camera::{reset_camera, MainCamera},
+    fast_rainbow_material::SimpleMesh2d,
+    flow_field::FlowField,
+    merging::MergeMode,
+    obstacles::{Obstacle, ObstacleEditor},
+    pool::DotPool,
+    presets::{FollowsPreset, SpawnPresets},
+    selection::Selection,
+    size_and_lifetime::{CollisionDamage, DrainInput},
+    soft_body::SoftBody,
+    spawner::Spawner,
+    steering::{Flock, SteeringTarget},
+    throwing::Throw,
+    PlacedSpawner, PointerTool, SimRng,
+};
+
+//
+//
+// Plugin
+
+/// F7 starts and stops recording, F8 plays the recording back.
+/// Both reseed `SimRng` and start the next frame from the world the lesson starts with,
+/// the replay feeds the recorded input and frame times back to every system
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Replay>()
+            .init_resource::<FreshStart>()
+            .add_system_to_stage(
+                CoreStage::First,
+                fresh_start_system.exclusive_system().at_start(),
+            )
+            .add_system_to_stage(CoreStage::PreUpdate, replay_system.before(InputSystem))
+            .add_system_to_stage(CoreStage::PreUpdate, record_system.after(InputSystem))
+            .add_system(control_system);
+    }
+}
+
+const RECORDING_PATH: &str = "recordings/lesson_2.replay.ron";
+const RECORD_KEY: KeyCode = KeyCode::F7;
+const REPLAY_KEY: KeyCode = KeyCode::F8;
+
+//
+//
+// Resources
+
+#[derive(Serialize, Deserialize)]
+pub struct Recording {
+    pub seed: u64,
+    /// Logical size of the primary window, it sets the physics bounds
+    pub window: Option<Vec2>,
+    pub frames: Vec<InputFrame>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct InputFrame {
+    /// Seconds since the previous frame
+    pub dt: f32,
+    /// Logical cursor position in the primary window
+    pub cursor: Option<Vec2>,
+    pub keys: Vec<KeyEvent>,
+    pub buttons: Vec<ButtonEvent>,
+    /// Moves the camera, which changes where the cursor points in the world
+    pub wheel: Vec<WheelEvent>,
+}
+
+/// Bevy's input events cannot be serialized, these copy their fields
+#[derive(Serialize, Deserialize)]
+pub struct KeyEvent {
+    pub scan_code: u32,
+    pub key_code: Option<KeyCode>,
+    pub state: ButtonState,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ButtonEvent {
+    pub button: MouseButton,
+    pub state: ButtonState,
+}
+
+impl From<&KeyboardInput> for KeyEvent {
+    fn from(event: &KeyboardInput) -> Self {
+        KeyEvent {
+            scan_code: event.scan_code,
+            key_code: event.key_code,
+            state: event.state,
+        }
+    }
+}
+
+impl From<&KeyEvent> for KeyboardInput {
+    fn from(event: &KeyEvent) -> Self {
+        KeyboardInput {
+            scan_code: event.scan_code,
+            key_code: event.key_code,
+            state: event.state,
+        }
+    }
+}
+
+impl From<&MouseButtonInput> for ButtonEvent {
+    fn from(event: &MouseButtonInput) -> Self {
+        ButtonEvent {
+            button: event.button,
+            state: event.state,
+        }
+    }
+}
+
+impl From<&ButtonEvent> for MouseButtonInput {
+    fn from(event: &ButtonEvent) -> Self {
+        MouseButtonInput {
+            button: event.button,
+            state: event.state,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WheelEvent {
+    /// Scrolled in lines rather than pixels
+    pub lines: bool,
+    pub x: f32,
+    pub y: f32,
+}
+
+impl From<&MouseWheel> for WheelEvent {
+    fn from(event: &MouseWheel) -> Self {
+        WheelEvent {
+            lines: event.unit == MouseScrollUnit::Line,
+            x: event.x,
+            y: event.y,
+        }
+    }
+}
+
+impl From<&WheelEvent> for MouseWheel {
+    fn from(event: &WheelEvent) -> Self {
+        MouseWheel {
+            unit: if event.lines {
+                MouseScrollUnit::Line
+            } else {
+                MouseScrollUnit::Pixel
+            },
+            x: event.x,
+            y: event.y,
+        }
+    }
+}
+
+#[derive(Default)]
+pub enum Replay {
+    #[default]
+    Idle,
+    Recording(Recording),
+    Replaying {
+        recording: Recording,
+        frame: usize,
+    },
+}
+
+/// Set when recording or replaying starts, the world is reset at the start of the next frame
+#[derive(Default)]
+struct FreshStart(bool);
+
+//
+//
+// Systems
+
+fn control_system(
+    keys: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    mut replay: ResMut<Replay>,
+    mut sim_rng: ResMut<SimRng>,
+    mut fresh_start: ResMut<FreshStart>,
+) {
+    if keys.just_pressed(RECORD_KEY) {
+        match mem::take(&mut *replay) {
+            Replay::Idle => {
+                let seed = rand::random();
+                *sim_rng = SimRng::new(seed);
+                fresh_start.0 = true;
+
+                *replay = Replay::Recording(Recording {
+                    seed,
+                    window: windows
+                        .get_primary()
+                        .map(|window| Vec2::new(window.width(), window.height())),
+                    frames: Vec::new(),
+                });
+                info!("recording");
+            }
+            Replay::Recording(recording) => match save(&recording, RECORDING_PATH) {
+                Ok(()) => info!(
+                    "recorded {} frames to {}",
+                    recording.frames.len(),
+                    RECORDING_PATH
+                ),
+                Err(err) => error!("could not save {}: {:?}", RECORDING_PATH, err),
+            },
+            replaying => *replay = replaying,
+        }
+    }
+
+    if keys.just_pressed(REPLAY_KEY) && matches!(*replay, Replay::Idle) {
+        match load(RECORDING_PATH) {
+            Ok(recording) => {
+                *sim_rng = SimRng::new(recording.seed);
+                fresh_start.0 = true;
+
+                info!("replaying {} frames", recording.frames.len());
+                *replay = Replay::Replaying {
+                    recording,
+                    frame: 0,
+                };
+            }
+            Err(err) => error!("could not load {}: {:?}", RECORDING_PATH, err),
+        }
+    }
+}
+
+/// Really despawns the dots instead of parking them, so recording and replay hand out
+/// entities in the same order, along with everything else the keys and the mouse
+/// change. Exclusive so nothing can still queue commands for the despawned entities
+fn fresh_start_system(world: &mut World) {
+    if !mem::take(&mut world.resource_mut::<FreshStart>().0) {
+        return;
+    }
+
+    let mut doomed: Vec<Entity> = world
+        .query_filtered::<Entity, (With<CircleCollider>, With<SimpleMesh2d>)>()
+        .iter(world)
+        .collect();
+    doomed.extend(world.query_filtered::<Entity, With<SoftBody>>().iter(world));
+    doomed.extend(world.query_filtered::<Entity, With<Flock>>().iter(world));
+    doomed.extend(
+        world
+            .query_filtered::<Entity, With<PlacedSpawner>>()
+            .iter(world),
+    );
+    doomed.extend(world.query_filtered::<Entity, With<Obstacle>>().iter(world));
+    for ntt in doomed {
+        world.despawn(ntt);
+    }
+    world.resource_mut::<DotPool>().reset();
+
+    *world.resource_mut::<FlowField>() = default();
+    *world.resource_mut::<MergeMode>() = default();
+    *world.resource_mut::<PointerTool>() = default();
+    *world.resource_mut::<Selection>() = default();
+    *world.resource_mut::<Throw>() = default();
+    *world.resource_mut::<DrainInput>() = default();
+    *world.resource_mut::<CollisionDamage>() = default();
+    *world.resource_mut::<SteeringTarget>() = default();
+    // Inserted by startup systems, the change makes the cursor spawner pick up the preset again
+    if let Some(mut presets) = world.get_resource_mut::<SpawnPresets>() {
+        presets.current = 0;
+    }
+    if let Some(mut editor) = world.get_resource_mut::<ObstacleEditor>() {
+        editor.reset();
+    }
+
+    // Fresh emission timing, the preset change gives the template back
+    let mut spawners = world.query_filtered::<&mut Spawner, With<FollowsPreset>>();
+    for mut spawner in spawners.iter_mut(world) {
+        *spawner = Spawner {
+            active: false,
+            ..default()
+        };
+    }
+
+    let mut cameras =
+        world.query_filtered::<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>();
+    for (mut trns, mut proj) in cameras.iter_mut(world) {
+        reset_camera(&mut trns, &mut proj);
+    }
+}
+
+fn record_system(
+    time: Res<Time>,
+    windows: Res<Windows>,
+    mut replay: ResMut<Replay>,
+    mut keyboard: EventReader<KeyboardInput>,
+    mut mouse: EventReader<MouseButtonInput>,
+    mut wheel: EventReader<MouseWheel>,
+) {
+    // Read the events either way so a new recording does not start with old ones
+    let keys: Vec<KeyEvent> = keyboard
+        .iter()
+        .filter(|event| event.key_code != Some(RECORD_KEY) && event.key_code != Some(REPLAY_KEY))
+        .map(KeyEvent::from)
+        .collect();
+    let buttons: Vec<ButtonEvent> = mouse.iter().map(ButtonEvent::from).collect();
+    let wheel: Vec<WheelEvent> = wheel.iter().map(WheelEvent::from).collect();
+
+    if let Replay::Recording(recording) = &mut *replay {
+        recording.frames.push(InputFrame {
+            dt: time.delta_seconds(),
+            cursor: windows
+                .get_primary()
+                .and_then(|window| window.cursor_position()),
+            keys,
+            buttons,
+            wheel,
+        });
+    }
+}
+
+/// Swaps the real input for the recorded one before bevy turns it into `Input` resources
+fn replay_system(
+    mut replay: ResMut<Replay>,
+    mut time: ResMut<Time>,
+    mut windows: ResMut<Windows>,
+    mut keyboard: ResMut<Events<KeyboardInput>>,
+    mut mouse: ResMut<Events<MouseButtonInput>>,
+    mut wheel: ResMut<Events<MouseWheel>>,
+) {
+    let (recording, frame) = match &mut *replay {
+        Replay::Replaying { recording, frame } => (recording, frame),
+        _ => return,
+    };
+
+    let input = match recording.frames.get(*frame) {
+        Some(input) => input,
+        None => {
+            info!("replay finished");
+            *replay = Replay::Idle;
+            return;
+        }
+    };
+    *frame += 1;
+
+    keyboard.clear();
+    mouse.clear();
+    wheel.clear();
+    for event in input.keys.iter() {
+        keyboard.send(event.into());
+    }
+    for event in input.buttons.iter() {
+        mouse.send(event.into());
+    }
+    for event in input.wheel.iter() {
+        wheel.send(event.into());
+    }
+
+    if let Some(window) = windows.get_primary_mut() {
+        let scale = window.scale_factor() as f32;
+
+        // The window may not get the size it asks for, so the size it reports is forced too
+        if let Some(size) = recording.window {
+            if window.width() != size.x || window.height() != size.y {
+                let physical = (size * scale).round();
+                window.set_resolution(size.x, size.y);
+                window.update_actual_size_from_backend(physical.x as u32, physical.y as u32);
+            }
+        }
+
+        window.update_cursor_physical_position_from_backend(
+            input.cursor.map(|pos| (pos * scale).as_dvec2()),
+        );
+    }
+
+    // Both updates measure from the same instant, so the delta is the recorded one
+    if let Some(now) = time.last_update() {
+        time.update_with_instant(now);
+        time.update_with_instant(now + Duration::from_secs_f32(input.dt));
+    }
+}
+
+//
+//
+// Helpers
+
+fn save(recording: &Recording, path: &str) -> anyhow::Result<()> {
+    let ron = ron::ser::to_string_pretty(recording, default())?;
+
+    if let Some(dir) = Path::new(path).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, ron)?;
+
+    Ok(())
+}
+
+fn load(path: &str) -> anyhow::Result<Recording> {
+    let ron = fs::read_to_string(path)?;
+    Ok(ron::de::from_str(&ron)?)
+}
@@ -0,0 +1,154 @@
+use std::{fs, path::Path};
+
+use bevy::{
+    ecs::entity::EntityMap, prelude::*, reflect::TypeRegistry, scene::serde::SceneDeserializer,
+};
+use serde::de::DeserializeSeed;
+
+use super::{
+    bevy_radial_physics::{CircleCollider, Force},
+    fast_rainbow_material::SimpleMesh2d,
+    pool::{DotPool, LiveDot, Pooled},
+    size_and_lifetime::{Died, Health, LifetimeSystem},
+    spawn_dot,
+};
+
+//
+//
+// Plugin
+
+/// F5 saves every dot to a scene file, F6 replaces the dots with the saved ones.
+/// Only what the physics needs is saved, loaded dots live forever
+pub struct SaveLoadPlugin;
+
+impl Plugin for SaveLoadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(save_system)
+            .add_system(load_system.label(LifetimeSystem::Deaths));
+    }
+}
+
+const SAVE_PATH: &str = "saves/lesson_2.scn.ron";
+
+type SavedDot<'a> = (
+    &'a Transform,
+    &'a Force,
+    &'a CircleCollider,
+    &'a Health,
+    &'a SimpleMesh2d,
+);
+
+//
+//
+// Systems
+
+fn save_system(
+    keys: Res<Input<KeyCode>>,
+    type_registry: Res<TypeRegistry>,
+    dots: Query<SavedDot, Without<Pooled>>,
+) {
+    if !keys.just_pressed(KeyCode::F5) {
+        return;
+    }
+
+    // Copy the dots into their own world so the scene holds nothing else
+    let mut world = World::new();
+    let mut count = 0;
+    for (trns, frc, col, health, mesh) in dots.iter() {
+        // The selection is not saved
+        let mesh = SimpleMesh2d {
+            highlight: 0.0,
+            ..*mesh
+        };
+        world
+            .spawn()
+            .insert_bundle((*trns, *frc, *col, *health, mesh));
+        count += 1;
+    }
+
+    match save(&world, &type_registry, SAVE_PATH) {
+        Ok(()) => info!("saved {} dots to {}", count, SAVE_PATH),
+        Err(err) => error!("could not save {}: {:?}", SAVE_PATH, err),
+    }
+}
+
+fn load_system(
+    keys: Res<Input<KeyCode>>,
+    type_registry: Res<TypeRegistry>,
+    dots: Query<(Entity, &Transform, &Health), LiveDot>,
+    mut pool: ResMut<DotPool>,
+    mut deaths: EventWriter<Died>,
+    mut commands: Commands,
+) {
+    if !keys.just_pressed(KeyCode::F6) {
+        return;
+    }
+
+    let mut world = match load(&type_registry, SAVE_PATH) {
+        Ok(world) => world,
+        Err(err) => {
+            error!("could not load {}: {:?}", SAVE_PATH, err);
+            return;
+        }
+    };
+
+    pool.clear(dots.iter(), &mut deaths);
+
+    let mut count = 0;
+    let mut saved = world.query::<SavedDot>();
+    for (trns, frc, col, health, mesh) in saved.iter(&world) {
+        let dot = spawn_dot(
+            &mut commands,
+            &mut pool,
+            trns.translation,
+            trns.scale.x,
+            frc.velo,
+            mesh.t,
+        );
+
+        if let Some(dot) = dot {
+            commands
+                .entity(dot)
+                .insert(*trns)
+                .insert(*col)
+                .insert(*health)
+                .insert(*mesh);
+            count += 1;
+        }
+    }
+
+    info!("loaded {} dots from {}", count, SAVE_PATH);
+}
+
+//
+//
+// Helpers
+
+fn save(world: &World, type_registry: &TypeRegistry, path: &str) -> anyhow::Result<()> {
+    let scene = DynamicScene::from_world(world, type_registry);
+    let ron = scene.serialize_ron(type_registry)?;
+
+    if let Some(dir) = Path::new(path).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, ron)?;
+
+    Ok(())
+}
+
+/// Reads the scene into a world of its own, the caller turns it into real dots
+fn load(type_registry: &TypeRegistry, path: &str) -> anyhow::Result<World> {
+    let ron = fs::read_to_string(path)?;
+
+    let mut deserializer = ron::de::Deserializer::from_str(&ron)?;
+    let scene = SceneDeserializer {
+        type_registry: &type_registry.read(),
+    }
+    .deserialize(&mut deserializer)?;
+
+    let mut world = World::new();
+    world.insert_resource(type_registry.clone());
+    scene.write_to_world(&mut world, &mut EntityMap::default())?;
+
+    Ok(world)
+}
@@ -0,0 +1,183 @@
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::{
+    bevy_radial_physics::{Bounds, CircleCollider, Force, RadialPhysicsPlugin},
+    SimRng,
+};
+
+pub const SCENARIO_DIR: &str = "assets/scenarios";
+pub const SCENARIO_EXTENSION: &str = "scenario.ron";
+
+//
+//
+// Scenarios
+
+/// Exact starting layout for the physics plus what should hold after `steps` steps
+#[derive(Deserialize)]
+pub struct Scenario {
+    /// Seconds per step
+    #[serde(default = "default_dt")]
+    pub dt: f32,
+    pub steps: usize,
+    /// Defaults to the arena of a 1280x720 window
+    #[serde(default)]
+    pub bounds: Option<Bounds>,
+    pub dots: Vec<ScenarioDot>,
+    pub expect: Vec<Expectation>,
+}
+
+#[derive(Deserialize)]
+pub struct ScenarioDot {
+    pub pos: Vec2,
+    #[serde(default)]
+    pub velo: Vec2,
+    pub size: f32,
+}
+
+/// Every dot has the same mass, so momentum and energy are sums over velocities
+#[derive(Deserialize)]
+pub enum Expectation {
+    /// Sum of velocities stays within `tolerance` of the start, only holds without walls
+    Momentum { tolerance: f32 },
+    /// Sum of squared speeds changes by at most `tolerance` as a fraction of the start
+    Energy { tolerance: f32 },
+    /// Dot `dot` ends within `tolerance` of `pos`
+    Position {
+        dot: usize,
+        pos: Vec2,
+        tolerance: f32,
+    },
+    /// Dot `dot` ends within `tolerance` of `velo`
+    Velocity {
+        dot: usize,
+        velo: Vec2,
+        tolerance: f32,
+    },
+}
+
+fn default_dt() -> f32 {
+    1.0 / 60.0
+}
+
+impl Scenario {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let ron = fs::read_to_string(path)?;
+        Ok(ron::de::from_str(&ron)?)
+    }
+
+    /// Runs `RadialPhysicsPlugin` on its own, without a window and with a fixed step.
+    /// Returns the position and velocity of every dot at the end
+    pub fn simulate(&self) -> Vec<(Vec2, Vec2)> {
+        let mut app = App::new();
+
+        let start = Instant::now();
+        let mut time = Time::default();
+        time.update_with_instant(start);
+
+        app.insert_resource(time)
+            .insert_resource(SimRng::new(0))
+            .insert_resource(self.bounds.unwrap_or_default())
+            .add_plugin(RadialPhysicsPlugin);
+
+        let dots: Vec<Entity> = self
+            .dots
+            .iter()
+            .map(|dot| {
+                app.world
+                    .spawn()
+                    .insert_bundle((
+                        Transform {
+                            translation: dot.pos.extend(0.0),
+                            scale: Vec3::splat(dot.size),
+                            ..default()
+                        },
+                        CircleCollider { r: 0.5 },
+                        Force {
+                            velo: dot.velo.extend(0.0),
+                        },
+                    ))
+                    .id()
+            })
+            .collect();
+
+        let dt = Duration::from_secs_f32(self.dt);
+        for step in 1..=self.steps as u32 {
+            app.world
+                .resource_mut::<Time>()
+                .update_with_instant(start + dt * step);
+            app.update();
+        }
+
+        dots.iter()
+            .map(|ntt| {
+                let trns = app.world.get::<Transform>(*ntt).unwrap();
+                let frc = app.world.get::<Force>(*ntt).unwrap();
+                (trns.translation.truncate(), frc.velo.truncate())
+            })
+            .collect()
+    }
+
+    /// One line for every expectation the end state breaks
+    pub fn check(&self, end: &[(Vec2, Vec2)]) -> Vec<String> {
+        let start: Vec<(Vec2, Vec2)> = self.dots.iter().map(|dot| (dot.pos, dot.velo)).collect();
+
+        let momentum =
+            |dots: &[(Vec2, Vec2)]| dots.iter().fold(Vec2::ZERO, |sum, (_, velo)| sum + *velo);
+        let energy = |dots: &[(Vec2, Vec2)]| {
+            dots.iter()
+                .map(|(_, velo)| velo.length_squared())
+                .sum::<f32>()
+        };
+
+        let mut failures = Vec::new();
+        for expectation in self.expect.iter() {
+            match *expectation {
+                Expectation::Momentum { tolerance } => {
+                    let (before, after) = (momentum(&start), momentum(end));
+                    if before.distance(after) > tolerance {
+                        failures.push(format!("momentum went from {} to {}", before, after));
+                    }
+                }
+                Expectation::Energy { tolerance } => {
+                    let (before, after) = (energy(&start), energy(end));
+                    if (after - before).abs() > before * tolerance {
+                        failures.push(format!("energy went from {} to {}", before, after));
+                    }
+                }
+                Expectation::Position {
+                    dot,
+                    pos,
+                    tolerance,
+                } => match end.get(dot) {
+                    Some((end_pos, _)) if end_pos.distance(pos) <= tolerance => {}
+                    Some((end_pos, _)) => failures.push(format!(
+                        "dot {} ended at {} instead of {}",
+                        dot, end_pos, pos
+                    )),
+                    None => failures.push(format!("there is no dot {}", dot)),
+                },
+                Expectation::Velocity {
+                    dot,
+                    velo,
+                    tolerance,
+                } => match end.get(dot) {
+                    Some((_, end_velo)) if end_velo.distance(velo) <= tolerance => {}
+                    Some((_, end_velo)) => failures.push(format!(
+                        "dot {} ended moving at {} instead of {}",
+                        dot, end_velo, velo
+                    )),
+                    None => failures.push(format!("there is no dot {}", dot)),
+                },
+            }
+        }
+
+        failures
+    }
+}
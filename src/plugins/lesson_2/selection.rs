@@ -0,0 +1,219 @@
+use bevy::prelude::*;
+
+use super::{
+    bevy_radial_physics::{
+        CircleCollider, Force, PhysicsSystem, PolygonCollider, SegmentCollider, StaticShape,
+    },
+    camera::WorldCursor,
+    fast_rainbow_material::SimpleMesh2d,
+    obstacles::Obstacle,
+    pool::Pooled,
+    size_and_lifetime::{DeathCause, Died, Health, LifetimeSystem},
+    PointerTool,
+};
+
+//
+//
+// Plugin
+
+/// With the select tool a plain click picks the dot under the cursor and dragging moves it,
+/// Delete removes the selected dot. Right click anywhere but on an obstacle
+/// pushes everything nearby away from the cursor
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Selection>()
+            .init_resource::<Explosion>()
+            .add_system(
+                select_system
+                    .label(LifetimeSystem::Deaths)
+                    .before(PhysicsSystem::Movement),
+            )
+            .add_system(explosion_system.before(PhysicsSystem::Movement));
+    }
+}
+
+//
+//
+// Resources
+
+#[derive(Default)]
+pub struct Selection {
+    pub entity: Option<Entity>,
+    /// Dot position minus cursor position while dragging
+    grab: Option<Vec2>,
+}
+
+pub struct Explosion {
+    pub radius: f32,
+    /// Speed given to dots right at the cursor, it falls off to 0 at `radius`
+    pub strength: f32,
+}
+
+impl Default for Explosion {
+    fn default() -> Self {
+        Explosion {
+            radius: 160.0,
+            strength: 600.0,
+        }
+    }
+}
+
+//
+//
+// Systems
+
+#[allow(clippy::too_many_arguments)]
+fn select_system(
+    time: Res<Time>,
+    world_cursor: Res<WorldCursor>,
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    tool: Res<PointerTool>,
+    mut selection: ResMut<Selection>,
+    mut dots: Query<
+        (
+            Entity,
+            &Transform,
+            &mut Force,
+            &CircleCollider,
+            &mut SimpleMesh2d,
+        ),
+        Without<Pooled>,
+    >,
+    healths: Query<&Health>,
+    mut deaths: EventWriter<Died>,
+) {
+    let cursor = world_cursor.0;
+
+    // Modifier clicks spawn soft bodies and draw obstacles, they leave the selection alone
+    let modifier = keys.pressed(KeyCode::LControl)
+        || keys.pressed(KeyCode::LAlt)
+        || keys.pressed(KeyCode::LShift);
+    let click = !modifier && buttons.just_pressed(MouseButton::Left);
+
+    // Dots that died or went back to the pool are no longer selected
+    if let Some(ntt) = selection.entity {
+        if dots.get(ntt).is_err() {
+            selection.entity = None;
+            selection.grab = None;
+        }
+    }
+
+    let mut picked = selection.entity;
+
+    if *tool != PointerTool::Select {
+        picked = None;
+    } else if click {
+        // The dot closest to the cursor among the ones under it
+        picked = cursor.and_then(|cursor| {
+            dots.iter()
+                .map(|(ntt, trns, _, col, _)| {
+                    let pos = trns.translation.truncate();
+                    (ntt, pos.distance(cursor) - col.r * trns.scale.x)
+                })
+                .filter(|(_, gap)| *gap <= 0.0)
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(ntt, _)| ntt)
+        });
+    }
+
+    if picked != selection.entity {
+        for ntt in [selection.entity, picked].into_iter().flatten() {
+            if let Ok((.., mut mesh)) = dots.get_mut(ntt) {
+                mesh.highlight = if Some(ntt) == picked { 1.0 } else { 0.0 };
+            }
+        }
+        selection.entity = picked;
+    }
+
+    let ntt = match selection.entity {
+        Some(ntt) => ntt,
+        None => {
+            selection.grab = None;
+            return;
+        }
+    };
+
+    if keys.just_pressed(KeyCode::Delete) {
+        if let (Ok((_, trns, ..)), Ok(health)) = (dots.get(ntt), healths.get(ntt)) {
+            deaths.send(Died {
+                entity: ntt,
+                position: trns.translation,
+                size: health.max,
+                cause: DeathCause::Deleted,
+            });
+        }
+        selection.entity = None;
+        selection.grab = None;
+        return;
+    }
+
+    if click {
+        if let (Ok((_, trns, ..)), Some(cursor)) = (dots.get(ntt), cursor) {
+            selection.grab = Some(trns.translation.truncate() - cursor);
+        }
+    }
+    if !buttons.pressed(MouseButton::Left) {
+        selection.grab = None;
+    }
+
+    // Kinematic drag, the velocity is picked so this frame's movement lands the dot
+    // on the cursor. Releasing the button keeps the velocity and flings the dot
+    let dt = time.delta().as_secs_f32();
+    if let (Some(grab), Some(cursor)) = (selection.grab, cursor) {
+        if dt > 0.0 {
+            if let Ok((_, trns, mut frc, ..)) = dots.get_mut(ntt) {
+                let target = cursor + grab;
+                frc.velo = ((target - trns.translation.truncate()) / dt).extend(0.0);
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn explosion_system(
+    world_cursor: Res<WorldCursor>,
+    buttons: Res<Input<MouseButton>>,
+    explosion: Res<Explosion>,
+    mut query: Query<(&Transform, &mut Force), Without<Pooled>>,
+    obstacles: Query<
+        (
+            &Transform,
+            Option<&CircleCollider>,
+            Option<&SegmentCollider>,
+            Option<&PolygonCollider>,
+        ),
+        (With<Obstacle>, Without<Force>),
+    >,
+) {
+    if !buttons.just_pressed(MouseButton::Right) {
+        return;
+    }
+
+    let cursor = match world_cursor.0 {
+        Some(pos) => pos,
+        None => return,
+    };
+
+    // Right clicking an obstacle deletes it instead
+    let on_obstacle = obstacles.iter().any(|(trns, circle, segment, polygon)| {
+        StaticShape::from_components(trns, circle, segment, polygon)
+            .is_some_and(|shape| shape.contact(cursor, 1.0).is_some())
+    });
+    if on_obstacle {
+        return;
+    }
+
+    for (trns, mut frc) in query.iter_mut() {
+        let away = trns.translation.truncate() - cursor;
+        let dist = away.length();
+        if dist >= explosion.radius {
+            continue;
+        }
+
+        let falloff = 1.0 - dist / explosion.radius;
+        frc.velo += (away.normalize_or_zero() * explosion.strength * falloff).extend(0.0);
+    }
+}
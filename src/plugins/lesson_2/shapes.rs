@@ -1,20 +1,20 @@
 use std::f32::consts::PI;
 
 use bevy::{
-    prelude::{Color, Mesh},
+    prelude::{Color, Mesh, Vec2},
     render::mesh::{Indices, PrimitiveTopology},
 };
 
-pub fn create_circle(num_points: usize, color: Color) -> Mesh {
+pub fn create_circle(num_points: usize, _color: Color) -> Mesh {
     let mut star = Mesh::new(PrimitiveTopology::TriangleList);
 
     // Positions
     let mut v_pos = vec![[0.0, 0.0, 0.0]; num_points + 1];
-    for i in 1..=num_points {
+    for (i, pos) in v_pos.iter_mut().enumerate().skip(1) {
         let t = i as f32 / num_points as f32 * PI * 2.0;
         let x = t.sin() * 0.5;
         let y = t.cos() * 0.5;
-        v_pos[i] = [x, y, 0.0];
+        *pos = [x, y, 0.0];
     }
     star.insert_attribute(Mesh::ATTRIBUTE_POSITION, v_pos);
 
@@ -33,6 +33,30 @@ pub fn create_circle(num_points: usize, color: Color) -> Mesh {
     star
 }
 
+/// Triangle fan over a convex outline, with the normals and uvs `Mesh2d` expects
+pub fn create_polygon(points: &[Vec2]) -> Mesh {
+    let mut polygon = Mesh::new(PrimitiveTopology::TriangleList);
+
+    // Positions
+    let v_pos: Vec<[f32; 3]> = points.iter().map(|p| [p.x, p.y, 0.0]).collect();
+    polygon.insert_attribute(Mesh::ATTRIBUTE_POSITION, v_pos);
+
+    // Normals and uvs
+    let v_normal: Vec<[f32; 3]> = vec![[0.0, 0.0, 1.0]; points.len()];
+    polygon.insert_attribute(Mesh::ATTRIBUTE_NORMAL, v_normal);
+    let v_uv: Vec<[f32; 2]> = vec![[0.0, 0.0]; points.len()];
+    polygon.insert_attribute(Mesh::ATTRIBUTE_UV_0, v_uv);
+
+    // Indices
+    let mut indices = Vec::new();
+    for i in 1..points.len().saturating_sub(1) as u32 {
+        indices.extend_from_slice(&[0, i, i + 1]);
+    }
+    polygon.set_indices(Some(Indices::U32(indices)));
+
+    polygon
+}
+
 pub fn create_star(center_r: f32, spike_delata: f32) -> Mesh {
     // Let's define the mesh for the object we want to draw: a nice star.
     // We will specify here what kind of topology is used to define the mesh,
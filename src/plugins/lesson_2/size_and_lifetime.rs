@@ -1,4 +1,21 @@
-use bevy::prelude::*;
+use std::{f32::consts::TAU, mem, time::Instant};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics},
+    prelude::*,
+    utils::HashSet,
+};
+use rand::Rng;
+
+use super::{
+    bevy_radial_physics::{Collision, Force, PhysicsSystem},
+    fast_rainbow_material::SimpleMesh2d,
+    perf_log::{PerfLogDiagnostics, PerfLogStat},
+    pool::{DotPool, Pooled},
+    spawn_dot,
+    trace_export::TraceSpans,
+    SimRng,
+};
 
 //
 //
@@ -6,43 +23,465 @@ use bevy::prelude::*;
 
 pub struct SizeAndLifetimePlugin;
 
+impl SizeAndLifetimePlugin {
+    /// Milliseconds spent in `lifetime_system`
+    pub const LIFETIME_TIME: DiagnosticId =
+        DiagnosticId::from_u128(301873361843207626930586219307125840227);
+}
+
 impl Plugin for SizeAndLifetimePlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(lifetime_system);
+        app.register_type::<Health>()
+            .init_resource::<DrainInput>()
+            .init_resource::<CollisionDamage>()
+            .init_resource::<PerfLogDiagnostics>()
+            .init_resource::<TraceSpans>()
+            .add_event::<Damaged>()
+            .add_event::<Died>()
+            .add_startup_system(init_system)
+            .add_system(
+                damage_system
+                    .after(PhysicsSystem::Movement)
+                    .before(lifetime_system),
+            )
+            .add_system(lifetime_system.label(LifetimeSystem::Deaths))
+            .add_system(
+                split_on_death_system
+                    .label(LifetimeSystem::Split)
+                    .after(LifetimeSystem::Deaths),
+            );
+
+        app.world
+            .resource_mut::<PerfLogDiagnostics>()
+            .0
+            .push((Self::LIFETIME_TIME, PerfLogStat::Average));
     }
 }
 
+/// Every system that sends `Died` goes into `Deaths`, so `Split` sees each death
+/// in the same frame, before `PoolPlugin` reaps the entity in `PostUpdate`
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum LifetimeSystem {
+    Deaths,
+    Split,
+}
+
 //
 //
 // Components
 
-#[derive(Component)]
+#[derive(Component, Reflect, Default, Clone, Copy)]
+#[reflect(Component)]
 pub struct Health {
-    pub value: f32
+    pub value: f32,
+    /// Health when the dot was whole, `Died` reports it as the size
+    /// since a dying dot has shrunk to nothing
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(value: f32) -> Self {
+        Health { value, max: value }
+    }
+}
+
+/// Seconds an entity has left to live, `ttl` can be infinite
+#[derive(Component, Clone, Copy)]
+pub struct Lifetime {
+    pub ttl: f32,
+    pub elapsed: f32,
+}
+
+impl Lifetime {
+    pub fn new(ttl: f32) -> Self {
+        Lifetime { ttl, elapsed: 0.0 }
+    }
+
+    pub fn forever() -> Self {
+        Lifetime::new(f32::INFINITY)
+    }
+
+    /// Goes from 0 at spawn to 1 at death, immortal entities stay at 0
+    pub fn age(&self) -> f32 {
+        if self.ttl.is_finite() && self.ttl > 0.0 {
+            (self.elapsed / self.ttl).min(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// What happens to an entity over its lifetime, sampled with `Lifetime::age`
+#[derive(Component, Clone)]
+pub struct LifetimeCurves {
+    /// Multiplies `Health` to get the scale
+    pub scale: Curve,
+    /// Added on top of the animated color offset
+    pub color_offset: Curve,
+    pub alpha: Curve,
+}
+
+impl Default for LifetimeCurves {
+    fn default() -> Self {
+        LifetimeCurves {
+            scale: Curve::constant(1.0),
+            color_offset: Curve::constant(0.0),
+            alpha: Curve::constant(1.0),
+        }
+    }
+}
+
+impl LifetimeCurves {
+    /// Pops in, shifts its color along the way and fades out at the end
+    pub fn fade() -> Self {
+        LifetimeCurves {
+            scale: Curve::new(vec![(0.0, 0.0), (0.05, 1.0), (0.9, 1.0), (1.0, 0.6)]),
+            color_offset: Curve::new(vec![(0.0, 0.0), (1.0, 1.0)]),
+            alpha: Curve::new(vec![(0.0, 1.0), (0.7, 1.0), (1.0, 0.0)]),
+        }
+    }
+}
+
+/// Soaks up collision damage
+#[derive(Component, Clone, Copy)]
+pub struct Armor {
+    /// Taken off every hit
+    pub absorb: f32,
+    /// Seconds the entity can not be hurt after a hit
+    pub invulnerability: f32,
+    pub invulnerable_for: f32,
+}
+
+impl Armor {
+    pub fn new(absorb: f32, invulnerability: f32) -> Self {
+        Armor {
+            absorb,
+            invulnerability,
+            invulnerable_for: 0.0,
+        }
+    }
+}
+
+/// Piecewise linear curve through `(x, y)` keys, flat outside of them
+#[derive(Clone)]
+pub struct Curve {
+    keys: Vec<(f32, f32)>,
+}
+
+impl Curve {
+    pub fn new(mut keys: Vec<(f32, f32)>) -> Self {
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Curve { keys }
+    }
+
+    pub fn constant(value: f32) -> Self {
+        Curve::new(vec![(0.0, value)])
+    }
+
+    pub fn sample(&self, x: f32) -> f32 {
+        let i = self.keys.partition_point(|key| key.0 <= x);
+
+        if i == 0 {
+            self.keys.first().map_or(0.0, |key| key.1)
+        } else if i == self.keys.len() {
+            self.keys[i - 1].1
+        } else {
+            let (x0, y0) = self.keys[i - 1];
+            let (x1, y1) = self.keys[i];
+            y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+        }
+    }
+}
+
+/// Breaks into smaller dots when it dies, they keep its velocity plus `spread`
+#[derive(Component, Clone, Copy)]
+pub struct SplitOnDeath {
+    pub children: u32,
+    pub spread: f32,
 }
 
 //
 //
 // Resources
 
+/// Drains `Health` from everything while the key is held, `None` turns it off
+pub struct DrainInput {
+    pub key: Option<KeyCode>,
+    pub rate: f32,
+}
+
+impl Default for DrainInput {
+    fn default() -> Self {
+        DrainInput {
+            key: Some(KeyCode::Space),
+            rate: 32.0,
+        }
+    }
+}
+
+/// Turns impact speed into lost `Health`
+pub struct CollisionDamage {
+    pub enabled: bool,
+    /// Impacts slower than this do no damage
+    pub min_impact: f32,
+    /// Damage per unit of impact speed above `min_impact`
+    pub per_impact: f32,
+}
+
+impl Default for CollisionDamage {
+    fn default() -> Self {
+        CollisionDamage {
+            enabled: true,
+            min_impact: 100.0,
+            per_impact: 0.02,
+        }
+    }
+}
+
+//
+//
+// Events
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeathCause {
+    /// Outlived its `Lifetime`
+    Expired,
+    /// Ran out of `Health`
+    Drained,
+    /// Lost the last of its `Health` in a collision
+    Damaged,
+    /// Merged into a bigger dot
+    Absorbed,
+    /// Caught by a predator
+    Eaten,
+    /// Ran out of energy in the ecosystem
+    Starved,
+    /// Made room for new dots over the population cap
+    Evicted,
+    /// Removed for a fresh start when loading a save
+    Cleared,
+    /// Picked and deleted by hand
+    Deleted,
+}
+
+pub struct Damaged {
+    pub entity: Entity,
+    pub amount: f32,
+}
+
+/// Sent instead of despawning, `PoolPlugin` parks or despawns the entity at the end of the frame
+pub struct Died {
+    pub entity: Entity,
+    pub position: Vec3,
+    pub size: f32,
+    pub cause: DeathCause,
+}
+
 //
 //
 // Systems
 
+fn init_system(diagnostics: Option<ResMut<Diagnostics>>) {
+    if let Some(mut diagnostics) = diagnostics {
+        diagnostics.add(
+            Diagnostic::new(SizeAndLifetimePlugin::LIFETIME_TIME, "lifetime", 20).with_suffix("ms"),
+        );
+    }
+}
+
+/// Only the first frame of a contact hurts, overlapping dots collide every frame
+fn damage_system(
+    t: Res<Time>,
+    settings: Res<CollisionDamage>,
+    mut collisions: EventReader<Collision>,
+    mut query: Query<(&mut Health, Option<&mut Armor>)>,
+    mut damaged: EventWriter<Damaged>,
+    mut touching: Local<HashSet<(Entity, Entity)>>,
+) {
+    let dt = t.delta().as_secs_f32();
+
+    query.for_each_mut(|(_, armor)| {
+        if let Some(mut armor) = armor {
+            armor.invulnerable_for = (armor.invulnerable_for - dt).max(0.0);
+        }
+    });
+
+    let was_touching = mem::take(&mut *touching);
+    for collision in collisions.iter() {
+        let pair = if collision.a < collision.b {
+            (collision.a, collision.b)
+        } else {
+            (collision.b, collision.a)
+        };
+        // Same contact as last frame, or the same pair twice this frame
+        if !touching.insert(pair) || was_touching.contains(&pair) {
+            continue;
+        }
+        if !settings.enabled {
+            continue;
+        }
+
+        let amount = (collision.impact - settings.min_impact).max(0.0) * settings.per_impact;
+        if amount <= 0.0 {
+            continue;
+        }
+
+        for ntt in [collision.a, collision.b] {
+            if let Ok((mut health, armor)) = query.get_mut(ntt) {
+                let mut amount = amount;
+
+                if let Some(mut armor) = armor {
+                    if armor.invulnerable_for > 0.0 {
+                        continue;
+                    }
+                    armor.invulnerable_for = armor.invulnerability;
+                    amount = (amount - armor.absorb).max(0.0);
+                }
+
+                if amount > 0.0 {
+                    health.value -= amount;
+                    damaged.send(Damaged {
+                        entity: ntt,
+                        amount,
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn lifetime_system(
     t: Res<Time>,
     buttons: Res<Input<KeyCode>>,
-    mut query: Query<(Entity, &mut Transform, &mut Health)>,
-    mut commands: Commands,
+    drain: Res<DrainInput>,
+    mut query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Health,
+            &mut Lifetime,
+            Option<&LifetimeCurves>,
+            Option<&mut SimpleMesh2d>,
+        ),
+        Without<Pooled>,
+    >,
+    mut damaged: EventReader<Damaged>,
+    mut deaths: EventWriter<Died>,
+    diagnostics: Option<ResMut<Diagnostics>>,
+    spans: Res<TraceSpans>,
 ) {
-    if buttons.pressed(KeyCode::Space) {
-        query.for_each_mut(|(ntt, mut trns, mut health)| {
-            if health.value <= 0.0 {
-                commands.entity(ntt).despawn();
-            } else {
-                trns.scale = Vec3::splat(health.value);
-                health.value -= t.delta().as_secs_f32() * 32.0;
+    let _span = spans.enter("lifetime_system");
+    let start = Instant::now();
+
+    let dt = t.delta().as_secs_f32();
+    let draining = drain.key.is_some_and(|key| buttons.pressed(key));
+    let hit: HashSet<Entity> = damaged.iter().map(|damage| damage.entity).collect();
+
+    query.for_each_mut(|(ntt, mut trns, mut health, mut lifetime, curves, mesh)| {
+        let prev_age = lifetime.age();
+        lifetime.elapsed += dt;
+
+        if draining {
+            health.value -= dt * drain.rate;
+        }
+
+        if health.value <= 0.0 || lifetime.elapsed >= lifetime.ttl {
+            deaths.send(Died {
+                entity: ntt,
+                position: trns.translation,
+                size: health.max,
+                cause: if lifetime.elapsed >= lifetime.ttl {
+                    DeathCause::Expired
+                } else if hit.contains(&ntt) {
+                    DeathCause::Damaged
+                } else {
+                    DeathCause::Drained
+                },
+            });
+            return;
+        }
+
+        let age = lifetime.age();
+
+        if let Some(curves) = curves {
+            trns.scale = Vec3::splat(health.value * curves.scale.sample(age));
+
+            if let Some(mut mesh) = mesh {
+                mesh.t += curves.color_offset.sample(age) - curves.color_offset.sample(prev_age);
+                mesh.alpha = curves.alpha.sample(age);
             }
+        } else {
+            trns.scale = Vec3::splat(health.value);
+        }
+    });
+
+    if let Some(mut diagnostics) = diagnostics {
+        diagnostics.add_measurement(SizeAndLifetimePlugin::LIFETIME_TIME, || {
+            start.elapsed().as_secs_f64() * 1000.0
         });
     }
 }
+
+/// Runs before the dead entity is reaped, so it can still be read
+fn split_on_death_system(
+    mut deaths: EventReader<Died>,
+    query: Query<(
+        &SplitOnDeath,
+        &Force,
+        &SimpleMesh2d,
+        &Lifetime,
+        Option<&LifetimeCurves>,
+    )>,
+    mut pool: ResMut<DotPool>,
+    sim_rng: Res<SimRng>,
+    mut commands: Commands,
+) {
+    // Children smaller than this do not split any further
+    const MIN_SPLIT_SIZE: f32 = 8.0;
+
+    let mut rng = sim_rng.get("split");
+
+    for death in deaths.iter() {
+        if matches!(
+            death.cause,
+            DeathCause::Absorbed | DeathCause::Evicted | DeathCause::Cleared | DeathCause::Deleted
+        ) {
+            continue;
+        }
+
+        let (split, frc, mesh, lifetime, curves) = match query.get(death.entity) {
+            Ok(components) => components,
+            Err(_) => continue,
+        };
+
+        // Keep the total area
+        let size = death.size / (split.children as f32).sqrt();
+        let angle_offset = rng.gen::<f32>() * TAU;
+
+        for i in 0..split.children {
+            let angle = angle_offset + i as f32 / split.children as f32 * TAU;
+            let dir = Vec3::new(angle.cos(), angle.sin(), 0.0);
+
+            let child = match spawn_dot(
+                &mut commands,
+                &mut pool,
+                death.position + dir * size * 0.5,
+                size,
+                frc.velo + dir * split.spread,
+                mesh.t,
+            ) {
+                Some(child) => child,
+                None => break,
+            };
+
+            commands.entity(child).insert(Lifetime::new(lifetime.ttl));
+            if let Some(curves) = curves {
+                commands.entity(child).insert(curves.clone());
+            }
+            if size >= MIN_SPLIT_SIZE {
+                commands.entity(child).insert(*split);
+            }
+        }
+    }
+}
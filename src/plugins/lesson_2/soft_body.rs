@@ -0,0 +1,351 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use super::{
+    bevy_radial_physics::{Force, PhysicsSystem},
+    pool::{DotPool, Pooled},
+    size_and_lifetime::Armor,
+    spawn_dot,
+};
+
+//
+//
+// Plugin
+
+pub struct SoftBodyPlugin;
+
+impl Plugin for SoftBodyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(soft_body_system.before(PhysicsSystem::Movement))
+            .add_system(orphan_point_system);
+    }
+}
+
+//
+//
+// Components
+
+/// How a soft body holds its shape on top of its springs
+#[derive(Clone, Copy)]
+pub enum SoftBodyKind {
+    /// Blob filled with gas, the outline is pushed outwards when squashed
+    Pressure { pressure: f32 },
+    /// Jelly, every point is pulled towards the best fitting rest shape
+    ShapeMatching { stiffness: f32 },
+}
+
+#[derive(Clone, Copy)]
+pub struct Spring {
+    pub a: usize,
+    pub b: usize,
+    pub rest_len: f32,
+}
+
+/// Dots joined by springs, the dots themselves are ordinary physics entities
+#[derive(Component)]
+pub struct SoftBody {
+    pub kind: SoftBodyKind,
+    pub stiffness: f32,
+    pub damping: f32,
+    pub points: Vec<Entity>,
+    pub springs: Vec<Spring>,
+    /// Counter-clockwise loop of point indices
+    pub outline: Vec<usize>,
+    rest_shape: Vec<Vec2>,
+    rest_area: f32,
+}
+
+/// Marks the dots of a soft body, some systems must leave them alone
+#[derive(Component)]
+pub struct SoftBodyPoint {
+    pub body: Entity,
+}
+
+//
+//
+// Systems
+
+fn soft_body_system(
+    time: Res<Time>,
+    mut commands: Commands,
+    bodies: Query<(Entity, &SoftBody)>,
+    mut points: Query<(&Transform, &mut Force), Without<Pooled>>,
+) {
+    let dt = time.delta().as_secs_f32();
+
+    for (ntt, body) in bodies.iter() {
+        let mut pos = Vec::with_capacity(body.points.len());
+        let mut velo = Vec::with_capacity(body.points.len());
+        for point in body.points.iter() {
+            if let Ok((trns, frc)) = points.get(*point) {
+                pos.push(trns.translation.truncate());
+                velo.push(frc.velo.truncate());
+            }
+        }
+
+        if pos.len() != body.points.len() {
+            // One of the dots died, the rest carry on as loose dots
+            commands.entity(ntt).despawn();
+            continue;
+        }
+
+        let mut accel = vec![Vec2::ZERO; pos.len()];
+
+        // Springs
+        for spring in body.springs.iter() {
+            let delta = pos[spring.b] - pos[spring.a];
+            let len = delta.length();
+            if len == 0.0 {
+                continue;
+            }
+
+            let dir = delta / len;
+            let stretch = len - spring.rest_len;
+            let closing_velo = (velo[spring.b] - velo[spring.a]).dot(dir);
+            let f = dir * (stretch * body.stiffness + closing_velo * body.damping);
+
+            accel[spring.a] += f;
+            accel[spring.b] -= f;
+        }
+
+        match body.kind {
+            SoftBodyKind::Pressure { pressure } => {
+                let area = outline_area(&body.outline, &pos);
+                if area > f32::EPSILON {
+                    let p = pressure * (body.rest_area / area - 1.0);
+
+                    for i in 0..body.outline.len() {
+                        let a = body.outline[i];
+                        let b = body.outline[(i + 1) % body.outline.len()];
+
+                        // Outward normal of a counter-clockwise edge, as long as the edge
+                        let edge = pos[b] - pos[a];
+                        let normal = Vec2::new(edge.y, -edge.x);
+
+                        accel[a] += normal * p * 0.5;
+                        accel[b] += normal * p * 0.5;
+                    }
+                }
+            }
+            SoftBodyKind::ShapeMatching { stiffness } => {
+                let centroid = pos.iter().fold(Vec2::ZERO, |sum, p| sum + *p) / pos.len() as f32;
+
+                // Rotation that best lines the rest shape up with the current one
+                let mut cos = 0.0;
+                let mut sin = 0.0;
+                for (p, rest) in pos.iter().zip(body.rest_shape.iter()) {
+                    let d = *p - centroid;
+                    cos += rest.dot(d);
+                    sin += rest.perp_dot(d);
+                }
+                let rot = Vec2::new(cos, sin).normalize_or_zero();
+
+                for (i, rest) in body.rest_shape.iter().enumerate() {
+                    let goal = centroid
+                        + Vec2::new(
+                            rot.x * rest.x - rot.y * rest.y,
+                            rot.y * rest.x + rot.x * rest.y,
+                        );
+                    accel[i] += (goal - pos[i]) * stiffness;
+                }
+            }
+        }
+
+        for (point, a) in body.points.iter().zip(accel) {
+            if let Ok((_, mut frc)) = points.get_mut(*point) {
+                frc.velo += (a * dt).extend(0.0);
+            }
+        }
+    }
+}
+
+/// Points outlive their body as loose dots, which can merge like any other
+fn orphan_point_system(
+    mut commands: Commands,
+    bodies: Query<(), With<SoftBody>>,
+    points: Query<(Entity, &SoftBodyPoint)>,
+) {
+    for (ntt, point) in points.iter() {
+        if bodies.get(point.body).is_err() {
+            commands.entity(ntt).remove::<SoftBodyPoint>();
+        }
+    }
+}
+
+//
+//
+// Builders
+
+/// Spawns a closed ring of dots, every dot is tied to its two nearest neighbours on each side
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_soft_ring(
+    commands: &mut Commands,
+    pool: &mut DotPool,
+    rng: &mut impl Rng,
+    center: Vec3,
+    radius: f32,
+    count: usize,
+    dot_size: f32,
+    kind: SoftBodyKind,
+) -> Option<Entity> {
+    let positions: Vec<Vec2> = (0..count)
+        .map(|i| {
+            let a = i as f32 / count as f32 * PI * 2.0;
+            center.truncate() + Vec2::new(a.cos(), a.sin()) * radius
+        })
+        .collect();
+
+    let mut links = Vec::new();
+    for i in 0..count {
+        links.push((i, (i + 1) % count));
+        links.push((i, (i + 2) % count));
+    }
+
+    let outline = (0..count).collect();
+
+    spawn_soft_body(
+        commands, pool, rng, &positions, &links, outline, dot_size, kind,
+    )
+}
+
+/// Spawns a `cols` by `rows` lattice of dots with structural and shear springs
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_soft_grid(
+    commands: &mut Commands,
+    pool: &mut DotPool,
+    rng: &mut impl Rng,
+    center: Vec3,
+    cols: usize,
+    rows: usize,
+    spacing: f32,
+    dot_size: f32,
+    kind: SoftBodyKind,
+) -> Option<Entity> {
+    if cols == 0 || rows == 0 {
+        return None;
+    }
+
+    let origin =
+        center.truncate() - Vec2::new((cols - 1) as f32, (rows - 1) as f32) * spacing * 0.5;
+    let index = |col: usize, row: usize| row * cols + col;
+
+    let mut positions = Vec::with_capacity(cols * rows);
+    for row in 0..rows {
+        for col in 0..cols {
+            positions.push(origin + Vec2::new(col as f32, row as f32) * spacing);
+        }
+    }
+
+    let mut links = Vec::new();
+    for row in 0..rows {
+        for col in 0..cols {
+            if col + 1 < cols {
+                links.push((index(col, row), index(col + 1, row)));
+            }
+            if row + 1 < rows {
+                links.push((index(col, row), index(col, row + 1)));
+            }
+            if col + 1 < cols && row + 1 < rows {
+                links.push((index(col, row), index(col + 1, row + 1)));
+                links.push((index(col + 1, row), index(col, row + 1)));
+            }
+        }
+    }
+
+    // Walk the border counter-clockwise starting from the bottom left corner
+    let mut outline = Vec::new();
+    outline.extend((0..cols).map(|col| index(col, 0)));
+    outline.extend((1..rows).map(|row| index(cols - 1, row)));
+    outline.extend((0..cols - 1).rev().map(|col| index(col, rows - 1)));
+    outline.extend((1..rows - 1).rev().map(|row| index(0, row)));
+
+    spawn_soft_body(
+        commands, pool, rng, &positions, &links, outline, dot_size, kind,
+    )
+}
+
+//
+//
+// Helpers
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_soft_body(
+    commands: &mut Commands,
+    pool: &mut DotPool,
+    rng: &mut impl Rng,
+    positions: &[Vec2],
+    links: &[(usize, usize)],
+    outline: Vec<usize>,
+    dot_size: f32,
+    kind: SoftBodyKind,
+) -> Option<Entity> {
+    // A body missing some of its dots would fall apart right away
+    if positions.is_empty() || pool.room() < positions.len() {
+        return None;
+    }
+
+    // The whole body shares one color
+    let color_offset = rng.gen::<f32>() * PI;
+
+    let points: Vec<Entity> = positions
+        .iter()
+        .map(|pos| {
+            let point = spawn_dot(
+                commands,
+                pool,
+                pos.extend(0.0),
+                dot_size,
+                Vec3::ZERO,
+                color_offset,
+            )?;
+
+            // Neighbours bump into each other all the time
+            commands.entity(point).insert(Armor::new(4.0, 0.25));
+            Some(point)
+        })
+        .collect::<Option<_>>()?;
+
+    let springs = links
+        .iter()
+        .map(|(a, b)| Spring {
+            a: *a,
+            b: *b,
+            rest_len: positions[*a].distance(positions[*b]),
+        })
+        .collect();
+
+    let centroid = positions.iter().fold(Vec2::ZERO, |sum, p| sum + *p) / positions.len() as f32;
+    let rest_shape = positions.iter().map(|p| *p - centroid).collect();
+    let rest_area = outline_area(&outline, positions);
+
+    let body = commands.spawn().id();
+    for point in points.iter() {
+        commands.entity(*point).insert(SoftBodyPoint { body });
+    }
+
+    commands.entity(body).insert(SoftBody {
+        kind,
+        stiffness: 300.0,
+        damping: 8.0,
+        points,
+        springs,
+        outline,
+        rest_shape,
+        rest_area,
+    });
+
+    Some(body)
+}
+
+/// Shoelace formula, positive for counter-clockwise outlines
+fn outline_area(outline: &[usize], pos: &[Vec2]) -> f32 {
+    let mut area = 0.0;
+    for i in 0..outline.len() {
+        let a = pos[outline[i]];
+        let b = pos[outline[(i + 1) % outline.len()]];
+        area += a.perp_dot(b);
+    }
+    area * 0.5
+}
@@ -0,0 +1,42 @@
+use bevy::{prelude::*, utils::HashMap};
+
+/// Buckets indices by position so neighbour lookups only visit nearby cells
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<usize>>,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        SpatialGrid {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, pos: Vec2, index: usize) {
+        let cell = self.cell(pos);
+        self.cells.entry(cell).or_default().push(index);
+    }
+
+    /// Calls `f` for every index in the cells overlapping the square around `pos`,
+    /// callers still have to check the actual distance
+    pub fn for_each_near(&self, pos: Vec2, radius: f32, mut f: impl FnMut(usize)) {
+        let min = self.cell(pos - Vec2::splat(radius));
+        let max = self.cell(pos + Vec2::splat(radius));
+
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                if let Some(indices) = self.cells.get(&IVec2::new(x, y)) {
+                    for index in indices.iter() {
+                        f(*index);
+                    }
+                }
+            }
+        }
+    }
+
+    fn cell(&self, pos: Vec2) -> IVec2 {
+        (pos / self.cell_size).floor().as_ivec2()
+    }
+}
@@ -0,0 +1,247 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use super::{
+    bevy_radial_physics::CircleCollider,
+    pool::DotPool,
+    size_and_lifetime::{Health, Lifetime, LifetimeCurves},
+    spawn_dot, SimRng,
+};
+
+//
+//
+// Plugin
+
+pub struct SpawnerPlugin;
+
+impl Plugin for SpawnerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawner_system);
+    }
+}
+
+//
+//
+// Components
+
+/// Uniformly distributed value between `min` and `max`
+#[derive(Clone, Copy, Deserialize)]
+pub struct ValueRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl ValueRange {
+    pub fn new(min: f32, max: f32) -> Self {
+        ValueRange { min, max }
+    }
+
+    pub fn constant(value: f32) -> Self {
+        ValueRange::new(value, value)
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> f32 {
+        if self.min >= self.max {
+            self.min
+        } else {
+            rng.gen_range(self.min..=self.max)
+        }
+    }
+}
+
+/// Where around the spawner new dots appear, in the spawner's local space
+#[derive(Clone, Copy)]
+pub enum EmissionShape {
+    Point,
+    Circle { r: f32 },
+    Line { len: f32 },
+    Rect { size: Vec2 },
+}
+
+impl EmissionShape {
+    fn sample(&self, rng: &mut impl Rng) -> Vec2 {
+        match *self {
+            EmissionShape::Point => Vec2::ZERO,
+            EmissionShape::Circle { r } => {
+                // sqrt keeps the points evenly spread over the area
+                let a = rng.gen::<f32>() * TAU;
+                Vec2::new(a.cos(), a.sin()) * r * rng.gen::<f32>().sqrt()
+            }
+            EmissionShape::Line { len } => Vec2::new((rng.gen::<f32>() - 0.5) * len, 0.0),
+            EmissionShape::Rect { size } => {
+                Vec2::new(rng.gen::<f32>() - 0.5, rng.gen::<f32>() - 0.5) * size
+            }
+        }
+    }
+}
+
+/// Everything about a single spawned dot, missing fields in preset files use the defaults
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct DotTemplate {
+    pub size: ValueRange,
+    pub speed: ValueRange,
+    pub color_offset: ValueRange,
+    /// Infinite values spawn immortal dots
+    pub ttl: ValueRange,
+    /// Relative to the size, 0.5 touches the edge of the drawn circle
+    pub collider_radius: f32,
+    /// Starting `Health`, which also drives the scale, `None` starts at the size
+    pub health: Option<ValueRange>,
+}
+
+impl DotTemplate {
+    /// Samples everything but the size and velocity, which the caller picks
+    pub fn spawn(
+        &self,
+        commands: &mut Commands,
+        pool: &mut DotPool,
+        pos: Vec3,
+        size: f32,
+        velo: Vec3,
+        rng: &mut impl Rng,
+    ) -> Option<Entity> {
+        let color_offset = self.color_offset.sample(rng);
+
+        let dot = spawn_dot(commands, pool, pos, size, velo, color_offset)?;
+
+        if self.collider_radius != 0.5 {
+            commands.entity(dot).insert(CircleCollider {
+                r: self.collider_radius,
+            });
+        }
+        if let Some(health) = self.health {
+            commands.entity(dot).insert(Health::new(health.sample(rng)));
+        }
+
+        let ttl = self.ttl.sample(rng);
+        if ttl.is_finite() {
+            commands
+                .entity(dot)
+                .insert(Lifetime::new(ttl))
+                .insert(LifetimeCurves::fade());
+        }
+
+        Some(dot)
+    }
+}
+
+impl Default for DotTemplate {
+    fn default() -> Self {
+        DotTemplate {
+            size: ValueRange::new(4.0, 32.0),
+            speed: ValueRange::new(0.0, 280.0),
+            color_offset: ValueRange::new(0.0, PI),
+            ttl: ValueRange::constant(f32::INFINITY),
+            collider_radius: 0.5,
+            health: None,
+        }
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct Spawner {
+    pub active: bool,
+    /// Times per second the spawner fires
+    pub rate: f32,
+    /// Dots spawned every time the spawner fires
+    pub burst: u32,
+    pub shape: EmissionShape,
+    /// Middle of the velocity cone in radians, relative to the spawner's rotation
+    pub direction: f32,
+    /// Full width of the velocity cone in radians
+    pub cone: f32,
+    pub template: DotTemplate,
+    /// Shots owed, the fraction carries over to the next frame. Visible to the lesson
+    /// so spawners can be built with `..default()`
+    pub(super) accumulator: f32,
+}
+
+impl Default for Spawner {
+    fn default() -> Self {
+        Spawner {
+            active: true,
+            rate: 100.0,
+            burst: 1,
+            shape: EmissionShape::Point,
+            direction: 0.0,
+            cone: TAU,
+            template: DotTemplate::default(),
+            accumulator: 1.0,
+        }
+    }
+}
+
+impl Spawner {
+    /// Spawns up to `count` dots right away, whether the spawner is active or not
+    pub fn emit(
+        &self,
+        commands: &mut Commands,
+        pool: &mut DotPool,
+        rng: &mut impl Rng,
+        origin: &Transform,
+        count: u32,
+    ) {
+        for _ in 0..count {
+            self.spawn_one(commands, pool, origin, rng);
+        }
+    }
+
+    fn spawn_one(
+        &self,
+        commands: &mut Commands,
+        pool: &mut DotPool,
+        origin: &Transform,
+        rng: &mut impl Rng,
+    ) {
+        let offset = origin.rotation * self.shape.sample(rng).extend(0.0);
+        let pos = Vec3::new(origin.translation.x, origin.translation.y, 0.0) + offset;
+
+        let angle = self.direction + (rng.gen::<f32>() - 0.5) * self.cone;
+        let dir = origin.rotation * Vec3::new(angle.cos(), angle.sin(), 0.0);
+        let velo = dir * self.template.speed.sample(rng);
+
+        let size = self.template.size.sample(rng);
+
+        self.template.spawn(commands, pool, pos, size, velo, rng);
+    }
+}
+
+//
+//
+// Systems
+
+fn spawner_system(
+    time: Res<Time>,
+    mut spawners: Query<(&Transform, &mut Spawner)>,
+    mut pool: ResMut<DotPool>,
+    sim_rng: Res<SimRng>,
+    mut commands: Commands,
+) {
+    let dt = time.delta().as_secs_f32();
+    let mut rng = sim_rng.get("spawner");
+
+    for (trns, mut spawner) in spawners.iter_mut() {
+        if !spawner.active {
+            // Fire as soon as it is switched back on
+            spawner.accumulator = 1.0;
+            continue;
+        }
+
+        spawner.accumulator += dt * spawner.rate;
+
+        let shots = spawner.accumulator.floor();
+        spawner.accumulator -= shots;
+
+        spawner.emit(
+            &mut commands,
+            &mut pool,
+            &mut rng,
+            trns,
+            shots as u32 * spawner.burst,
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use bevy::{prelude::*, utils::HashSet};
+
+use super::{
+    bevy_radial_physics::{Force, PhysicsSystem},
+    spatial_grid::SpatialGrid,
+};
+
+//
+//
+// Plugin
+
+pub struct SteeringPlugin;
+
+impl Plugin for SteeringPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SteeringTarget>()
+            .add_system(steering_system.before(PhysicsSystem::Movement))
+            .add_system(empty_flock_system);
+    }
+}
+
+//
+//
+// Components
+
+#[derive(Component)]
+pub struct Boid {
+    pub flock: Entity,
+}
+
+/// Settings shared by every boid pointing at this entity
+#[derive(Component, Clone)]
+pub struct Flock {
+    pub perception: f32,
+    pub separation_distance: f32,
+    pub separation: f32,
+    pub alignment: f32,
+    pub cohesion: f32,
+    /// Weight of seeking the steering target, negative values flee from it
+    pub target: f32,
+    pub max_speed: f32,
+    pub max_accel: f32,
+}
+
+impl Default for Flock {
+    fn default() -> Self {
+        Flock {
+            perception: 48.0,
+            separation_distance: 16.0,
+            separation: 1.5,
+            alignment: 1.0,
+            cohesion: 1.0,
+            target: 0.0,
+            max_speed: 200.0,
+            max_accel: 400.0,
+        }
+    }
+}
+
+//
+//
+// Resources
+
+/// Point the flocks seek or flee, usually the cursor
+#[derive(Default)]
+pub struct SteeringTarget(pub Option<Vec2>);
+
+//
+//
+// Systems
+
+fn steering_system(
+    time: Res<Time>,
+    target: Res<SteeringTarget>,
+    flocks: Query<&Flock>,
+    mut boids: Query<(Entity, &Boid, &Transform, &mut Force)>,
+) {
+    let cell_size = flocks
+        .iter()
+        .fold(0.0, |max, flock| f32::max(max, flock.perception));
+    if cell_size <= 0.0 {
+        return;
+    }
+
+    // Snapshot so every boid steers from the same frame
+    let snapshot: Vec<(Entity, Entity, Vec2, Vec2)> = boids
+        .iter()
+        .map(|(ntt, boid, trns, frc)| {
+            (
+                ntt,
+                boid.flock,
+                trns.translation.truncate(),
+                frc.velo.truncate(),
+            )
+        })
+        .collect();
+
+    let mut grid = SpatialGrid::new(cell_size);
+    for (i, (_, _, pos, _)) in snapshot.iter().enumerate() {
+        grid.insert(*pos, i);
+    }
+
+    let dt = time.delta().as_secs_f32();
+
+    for (i, (ntt, flock_ntt, pos, velo)) in snapshot.iter().enumerate() {
+        let flock = match flocks.get(*flock_ntt) {
+            Ok(flock) => flock,
+            Err(_) => continue,
+        };
+
+        let mut separation = Vec2::ZERO;
+        let mut velo_sum = Vec2::ZERO;
+        let mut pos_sum = Vec2::ZERO;
+        let mut count = 0;
+
+        grid.for_each_near(*pos, flock.perception, |j| {
+            let (_, other_flock, other_pos, other_velo) = snapshot[j];
+            if j == i || other_flock != *flock_ntt {
+                return;
+            }
+
+            let away = *pos - other_pos;
+            let dist = away.length();
+            if dist > flock.perception {
+                return;
+            }
+
+            if dist > 0.0 && dist < flock.separation_distance {
+                separation += away / (dist * dist);
+            }
+
+            velo_sum += other_velo;
+            pos_sum += other_pos;
+            count += 1;
+        });
+
+        // Reynolds steering, each behavior asks for a velocity and steers towards it
+        let steer = |dir: Vec2| dir.normalize_or_zero() * flock.max_speed - *velo;
+
+        let mut accel = Vec2::ZERO;
+
+        if separation != Vec2::ZERO {
+            accel += steer(separation) * flock.separation;
+        }
+
+        if count > 0 {
+            accel += steer(velo_sum / count as f32) * flock.alignment;
+            accel += steer(pos_sum / count as f32 - *pos) * flock.cohesion;
+        }
+
+        if let Some(target) = target.0 {
+            if flock.target > 0.0 {
+                accel += steer(target - *pos) * flock.target;
+            } else if flock.target < 0.0 {
+                accel += steer(*pos - target) * -flock.target;
+            }
+        }
+
+        let accel = accel.clamp_length_max(flock.max_accel);
+
+        if let Ok((_, _, _, mut frc)) = boids.get_mut(*ntt) {
+            let velo = (*velo + accel * dt).clamp_length_max(flock.max_speed);
+            frc.velo = velo.extend(0.0);
+        }
+    }
+}
+
+/// A flock goes away with its last boid
+fn empty_flock_system(
+    mut commands: Commands,
+    flocks: Query<Entity, With<Flock>>,
+    boids: Query<&Boid>,
+) {
+    let alive: HashSet<Entity> = boids.iter().map(|boid| boid.flock).collect();
+    for ntt in flocks.iter() {
+        if !alive.contains(&ntt) {
+            commands.entity(ntt).despawn();
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use bevy::prelude::*;
+
+use super::{
+    bevy_radial_physics::Bounds, camera::WorldCursor, pool::DotPool, presets::FollowsPreset,
+    spawner::Spawner, PointerTool, SimRng,
+};
+
+//
+//
+// Plugin
+
+/// With the throw tool dragging pulls a slingshot back
+/// and releasing throws one dot the other way
+pub struct ThrowPlugin;
+
+impl Plugin for ThrowPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Throw>()
+            .add_startup_system(init_system)
+            .add_system(throw_system);
+    }
+}
+
+//
+//
+// Components
+
+#[derive(Component)]
+struct TrajectoryDot;
+
+//
+//
+// Resources
+
+pub struct Throw {
+    /// Velocity per pixel of drag
+    pub strength: f32,
+    /// Where the drag started and the size of the dot being thrown
+    drag: Option<(Vec2, f32)>,
+}
+
+impl Default for Throw {
+    fn default() -> Self {
+        Throw {
+            strength: 3.0,
+            drag: None,
+        }
+    }
+}
+
+const TRAJECTORY_DOTS: usize = 48;
+/// Seconds of flight between two trajectory dots
+const TRAJECTORY_STEP: f32 = 1.0 / 30.0;
+
+//
+//
+// Systems
+
+fn init_system(mut commands: Commands) {
+    for _ in 0..TRAJECTORY_DOTS {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(1.0, 1.0, 1.0, 0.5),
+                    custom_size: Some(Vec2::splat(3.0)),
+                    ..default()
+                },
+                visibility: Visibility { is_visible: false },
+                ..default()
+            })
+            .insert(TrajectoryDot);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn throw_system(
+    world_cursor: Res<WorldCursor>,
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    bounds: Res<Bounds>,
+    tool: Res<PointerTool>,
+    mut throw: ResMut<Throw>,
+    mut pool: ResMut<DotPool>,
+    sim_rng: Res<SimRng>,
+    spawners: Query<&Spawner, With<FollowsPreset>>,
+    mut trajectory: Query<(&mut Transform, &mut Visibility), With<TrajectoryDot>>,
+    mut commands: Commands,
+) {
+    let enabled = *tool == PointerTool::Throw;
+    if !enabled {
+        throw.drag = None;
+    }
+
+    let mut rng = sim_rng.get("throw");
+    let cursor = world_cursor.0;
+
+    // Same dots as the cursor spawner
+    let template = spawners
+        .iter()
+        .next()
+        .map(|spawner| spawner.template.clone())
+        .unwrap_or_default();
+
+    let modifier = keys.pressed(KeyCode::LControl)
+        || keys.pressed(KeyCode::LAlt)
+        || keys.pressed(KeyCode::LShift);
+
+    if enabled && !modifier && buttons.just_pressed(MouseButton::Left) {
+        if let Some(cursor) = cursor {
+            let size = template.size.sample(&mut rng);
+            throw.drag = Some((cursor, size));
+        }
+    }
+
+    let aim = match (throw.drag, cursor) {
+        (Some((start, size)), Some(cursor)) => {
+            Some((start, size, (start - cursor) * throw.strength))
+        }
+        _ => None,
+    };
+
+    if buttons.just_released(MouseButton::Left) {
+        if let Some((start, size, velo)) = aim {
+            template.spawn(
+                &mut commands,
+                &mut pool,
+                start.extend(0.0),
+                size,
+                velo.extend(0.0),
+                &mut rng,
+            );
+        }
+        throw.drag = None;
+    }
+
+    // Preview
+    let mut points = Vec::new();
+    if let Some((start, size, velo)) = aim.filter(|_| throw.drag.is_some()) {
+        let r = size * template.collider_radius;
+        let (mut pos, mut velo) = (start, velo);
+
+        for _ in 0..TRAJECTORY_DOTS {
+            pos += velo * TRAJECTORY_STEP;
+            bounce(&bounds, r, &mut pos, &mut velo);
+            points.push(pos);
+        }
+    }
+
+    for (i, (mut trns, mut visibility)) in trajectory.iter_mut().enumerate() {
+        if let Some(pos) = points.get(i) {
+            trns.translation = pos.extend(1.0);
+            visibility.is_visible = true;
+        } else {
+            visibility.is_visible = false;
+        }
+    }
+}
+
+//
+//
+// Helpers
+
+/// Reflects off the bounds the same way the physics does
+fn bounce(bounds: &Bounds, r: f32, pos: &mut Vec2, velo: &mut Vec2) {
+    if pos.x - r <= bounds.min.x || pos.x + r >= bounds.max.x {
+        velo.x = -velo.x;
+        pos.x = pos.x.clamp(bounds.min.x + r, bounds.max.x - r);
+    }
+
+    if pos.y - r <= bounds.min.y || pos.y + r >= bounds.max.y {
+        velo.y = -velo.y;
+        pos.y = pos.y.clamp(bounds.min.y + r, bounds.max.y - r);
+    }
+}
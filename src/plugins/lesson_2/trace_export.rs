@@ -0,0 +1,178 @@
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+    thread::{self, ThreadId},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    prelude::*,
+    utils::tracing::{info_span, span::EnteredSpan},
+};
+use serde::Serialize;
+
+use super::perf_export::{PerfExport, MEASUREMENTS_DIR};
+
+//
+//
+// Plugin
+
+/// F9 starts and stops recording `TraceSpans`, stopping writes a Chrome trace
+/// to `measurements/` that opens in chrome://tracing or Perfetto
+pub struct TraceExportPlugin;
+
+impl Plugin for TraceExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PerfExport>()
+            .init_resource::<TraceSpans>()
+            .add_system_to_stage(CoreStage::Last, export_system);
+    }
+}
+
+//
+//
+// Resources
+
+/// Spans of the expensive systems. They are always `tracing` spans, so they show up
+/// in Bevy's own trace output too, and are kept for the export while recording.
+/// Lives in both the main and the render world
+#[derive(Clone, Default)]
+pub struct TraceSpans(Arc<Mutex<Option<TraceRecording>>>);
+
+impl TraceSpans {
+    /// The span lasts until the guard is dropped
+    pub fn enter(&self, name: &'static str) -> TraceGuard<'_> {
+        TraceGuard {
+            spans: self,
+            name,
+            start: Instant::now(),
+            _span: info_span!("system", name).entered(),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.0.lock().unwrap().is_some()
+    }
+}
+
+pub struct TraceGuard<'a> {
+    spans: &'a TraceSpans,
+    name: &'static str,
+    start: Instant,
+    _span: EnteredSpan,
+}
+
+impl Drop for TraceGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(recording) = self.spans.0.lock().unwrap().as_mut() {
+            recording.push(self.name, self.start);
+        }
+    }
+}
+
+struct TraceRecording {
+    start: Instant,
+    /// Unix time in seconds, used in the file name
+    started: u64,
+    events: Vec<TraceEvent>,
+    /// Index is the thread id in the trace
+    threads: Vec<ThreadId>,
+}
+
+impl TraceRecording {
+    fn new() -> Self {
+        TraceRecording {
+            start: Instant::now(),
+            started: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+            events: Vec::new(),
+            threads: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, name: &'static str, start: Instant) {
+        let thread = thread::current().id();
+        let tid = match self.threads.iter().position(|id| *id == thread) {
+            Some(tid) => tid,
+            None => {
+                self.threads.push(thread);
+                self.threads.len() - 1
+            }
+        };
+
+        // Spans that were entered before the recording started begin at 0
+        let ts = start.saturating_duration_since(self.start);
+        self.events.push(TraceEvent {
+            name,
+            cat: "system",
+            ph: "X",
+            ts: ts.as_secs_f64() * 1e6,
+            dur: start.elapsed().as_secs_f64() * 1e6,
+            pid: 0,
+            tid,
+        });
+    }
+}
+
+/// Complete event of the Chrome trace format, times are in microseconds
+#[derive(Serialize)]
+struct TraceEvent {
+    name: &'static str,
+    cat: &'static str,
+    ph: &'static str,
+    ts: f64,
+    dur: f64,
+    pid: u32,
+    tid: usize,
+}
+
+#[derive(Serialize)]
+struct ChromeTrace<'a> {
+    #[serde(rename = "traceEvents")]
+    trace_events: &'a [TraceEvent],
+}
+
+//
+//
+// Systems
+
+fn export_system(keys: Res<Input<KeyCode>>, export: Res<PerfExport>, spans: Res<TraceSpans>) {
+    if !keys.just_pressed(KeyCode::F9) {
+        return;
+    }
+
+    let recording = spans.0.lock().unwrap().take();
+    let recording = match recording {
+        Some(recording) => recording,
+        None => {
+            *spans.0.lock().unwrap() = Some(TraceRecording::new());
+            info!("trace started");
+            return;
+        }
+    };
+
+    let path = format!(
+        "{}/{}_{}.trace.json",
+        MEASUREMENTS_DIR, export.lesson, recording.started
+    );
+    match save(&recording, &path) {
+        Ok(()) => info!("wrote {} spans to {}", recording.events.len(), path),
+        Err(err) => error!("could not write {}: {:?}", path, err),
+    }
+}
+
+//
+//
+// Helpers
+
+fn save(recording: &TraceRecording, path: &str) -> anyhow::Result<()> {
+    let trace = ChromeTrace {
+        trace_events: &recording.events,
+    };
+
+    fs::create_dir_all(MEASUREMENTS_DIR)?;
+    fs::write(path, serde_json::to_string(&trace)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use std::fs;
+
+use bevy_experiments::plugins::lesson_2::scenarios::{Scenario, SCENARIO_DIR, SCENARIO_EXTENSION};
+
+/// Every scenario file is a regression test for the physics solver
+#[test]
+fn scenarios() {
+    let mut count = 0;
+    let mut failures = Vec::new();
+
+    for entry in fs::read_dir(SCENARIO_DIR).unwrap() {
+        let path = entry.unwrap().path();
+        if !path.to_string_lossy().ends_with(SCENARIO_EXTENSION) {
+            continue;
+        }
+
+        let scenario = Scenario::load(&path).unwrap();
+        let end = scenario.simulate();
+        for failure in scenario.check(&end) {
+            failures.push(format!("{}: {}", path.display(), failure));
+        }
+        count += 1;
+    }
+
+    assert!(count > 0, "no scenarios in {}", SCENARIO_DIR);
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}